@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::{dmesg, mounts, source, util};
+
+/// 需要录制的 procfs 全局文件
+const PROC_FILES: &[&str] = &[
+    "stat",
+    "diskstats",
+    "meminfo",
+    "loadavg",
+    "uptime",
+    "net/dev",
+    "pressure/cpu",
+    "pressure/memory",
+    "pressure/io",
+    "self/mounts",
+    "self/cgroup",
+    "sys/kernel/hostname",
+    "sys/kernel/ostype",
+    "sys/kernel/osrelease",
+    "sys/kernel/version",
+    "sys/kernel/threads-max",
+    "sys/kernel/pid_max",
+];
+
+/// 需要录制的 /proc/<pid> 下的文件
+const PROCESS_FILES: &[&str] = &["stat", "status", "statm", "smaps_rollup", "limits", "io", "cmdline", "comm", "cgroup"];
+
+/// 需要录制的 /proc/<pid> 下的符号链接，只保存链接目标
+const PROCESS_LINKS: &[&str] = &["exe", "cwd"];
+
+/// 需要录制的 /proc/<pid>/task/<tid> 下的文件
+const TASK_FILES: &[&str] = &["stat", "status", "statm", "wchan", "syscall", "stack"];
+
+/// 需要录制的 cgroup v2 目录下的文件
+const CGROUP_FILES: &[&str] = &[
+    "cpu.stat",
+    "cpu.max",
+    "memory.current",
+    "memory.max",
+    "pids.current",
+    "pids.max",
+    "io.stat",
+];
+
+/// 快照根目录下保存主机标识的文件，procfs/sysfs 中没有机器 ID 与 IP
+const IDENTITY_FILE: &str = "identity.json";
+
+/// 快照根目录下保存各挂载点 statvfs 结果的文件，回放时无法对快照中的挂载点调用 statvfs
+const FS_STATS_FILE: &str = "statvfs.json";
+
+/// 录制时的主机标识，回放时用于生成 serverId 与 serverIp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotIdentity {
+    #[serde(rename = "machineId")]
+    pub machine_id: String,
+    #[serde(rename = "serverIp")]
+    pub server_ip: String,
+}
+
+/// 读取快照中的主机标识，快照根目录为 --proc-root 的上一级
+pub fn read_identity() -> Option<SnapshotIdentity> {
+    read_snapshot_file(IDENTITY_FILE)
+}
+
+/// 读取快照中录制的挂载点 statvfs 结果，键为挂载点，没有录制时为空
+pub fn read_fs_stats() -> HashMap<String, mounts::FsStats> {
+    read_snapshot_file(FS_STATS_FILE).unwrap_or_default()
+}
+
+// 读取快照根目录下的 JSON 文件
+fn read_snapshot_file<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    let root = source::current().proc_root().parent()?;
+    let content = std::fs::read_to_string(root.join(name)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 从当前数据源录制目录快照
+///
+/// 快照包含 proc 与 sys 两个子目录、主机标识及挂载点的 statvfs 结果，之后可通过 --proc-root/--sys-root 指向它们重新运行各采集器
+///
+/// # Returns
+/// * `usize` - 录制的文件数量
+pub fn capture_snapshot(output: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let source = source::current();
+    let proc_out = output.join("proc");
+    let sys_out = output.join("sys");
+    std::fs::create_dir_all(&proc_out)?;
+    std::fs::create_dir_all(&sys_out)?;
+
+    let mut count = 0;
+    for file in PROC_FILES {
+        count += copy_file(&source.proc_path(file), &proc_out.join(file)) as usize;
+    }
+    count += capture_processes(source.proc_root(), &proc_out);
+
+    let identity = SnapshotIdentity {
+        machine_id: util::machine_id(),
+        server_ip: util::server_ip(),
+    };
+    std::fs::write(output.join(IDENTITY_FILE), serde_json::to_string_pretty(&identity)?)?;
+    count += 1;
+
+    std::fs::write(output.join(FS_STATS_FILE), serde_json::to_string_pretty(&mounts::record_fs_stats())?)?;
+    count += 1;
+
+    // dmesg 输出保存在 procfs 快照根目录下
+    let (dmesg_output, _) = dmesg::collect_dmesg(None).unwrap_or_default();
+    std::fs::write(proc_out.join("dmesg"), dmesg_output)?;
+    count += 1;
+
+    count += capture_block_devices(&source.sys_path("block"), &sys_out.join("block"));
+    count += capture_matching(&source.sys_path("class/hwmon"), &sys_out.join("class/hwmon"), "hwmon", |name| {
+        name == "name"
+            || ["_input", "_label", "_max", "_crit"].iter().any(|suffix| name.ends_with(suffix))
+    });
+    count += capture_matching(&source.sys_path("class/thermal"), &sys_out.join("class/thermal"), "thermal_zone", |name| {
+        name == "type" || name == "temp" || name.starts_with("trip_point_")
+    });
+    count += capture_cgroups(source, &sys_out.join("fs/cgroup"));
+
+    Ok(count)
+}
+
+// 录制所有进程及其线程的文件
+fn capture_processes(proc_root: &Path, proc_out: &Path) -> usize {
+    let mut count = 0;
+    for pid_dir in numeric_entries(proc_root) {
+        let Some(pid) = pid_dir.file_name() else { continue };
+        let pid_out = proc_out.join(pid);
+        for file in PROCESS_FILES {
+            count += copy_file(&pid_dir.join(file), &pid_out.join(file)) as usize;
+        }
+        for link in PROCESS_LINKS {
+            count += copy_link(&pid_dir.join(link), &pid_out.join(link)) as usize;
+        }
+        // 文件描述符只记录链接目标，用于统计描述符类型
+        for fd in numeric_entries(&pid_dir.join("fd")) {
+            let Some(fd_name) = fd.file_name() else { continue };
+            count += copy_link(&fd, &pid_out.join("fd").join(fd_name)) as usize;
+        }
+        for task_dir in numeric_entries(&pid_dir.join("task")) {
+            let Some(tid) = task_dir.file_name() else { continue };
+            let task_out = pid_out.join("task").join(tid);
+            for file in TASK_FILES {
+                count += copy_file(&task_dir.join(file), &task_out.join(file)) as usize;
+            }
+        }
+    }
+    count
+}
+
+// 只记录带有物理设备的整盘，磁盘 IO 采集据此过滤设备
+fn capture_block_devices(block_dir: &Path, block_out: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(block_dir) else { return 0 };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().join("device").exists())
+        .filter(|entry| std::fs::create_dir_all(block_out.join(entry.file_name()).join("device")).is_ok())
+        .count()
+}
+
+// 录制 dir 下以 prefix 开头的子目录中满足条件的文件（sysfs 中多为符号链接，复制为普通目录）
+fn capture_matching(dir: &Path, out: &Path, prefix: &str, wanted: impl Fn(&str) -> bool) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else { return 0 };
+    let mut count = 0;
+    for entry in entries.flatten() {
+        let entry_name = entry.file_name();
+        if !entry_name.to_string_lossy().starts_with(prefix) {
+            continue;
+        }
+        let Ok(files) = std::fs::read_dir(entry.path()) else { continue };
+        for file in files.flatten() {
+            let file_name = file.file_name();
+            if wanted(&file_name.to_string_lossy()) {
+                count += copy_file(&file.path(), &out.join(&entry_name).join(&file_name)) as usize;
+            }
+        }
+    }
+    count
+}
+
+// 录制采集程序自身及各进程所在的 cgroup v2 目录（含祖先目录，pids.max 逐级生效）
+fn capture_cgroups(source: &source::Source, cgroup_out: &Path) -> usize {
+    let cgroup_root = source.sys_path("fs/cgroup");
+    let mut count = copy_file(&cgroup_root.join("cgroup.controllers"), &cgroup_out.join("cgroup.controllers")) as usize;
+
+    let pids = numeric_entries(source.proc_root())
+        .into_iter()
+        .filter_map(|dir| Some(dir.file_name()?.to_string_lossy().to_string()));
+    let dirs: std::collections::BTreeSet<_> = std::iter::once("self".to_string())
+        .chain(pids)
+        .filter_map(|pid| crate::cgroup::cgroup_dir_of(&pid))
+        .flat_map(|dir| crate::cgroup::with_ancestors(&dir))
+        .collect();
+
+    for dir in dirs {
+        let relative = dir.strip_prefix(&cgroup_root).unwrap_or(Path::new(""));
+        for file in CGROUP_FILES {
+            count += copy_file(&dir.join(file), &cgroup_out.join(relative).join(file)) as usize;
+        }
+    }
+    count
+}
+
+fn numeric_entries(dir: &Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .map(|entry| entry.path())
+        .collect()
+}
+
+// procfs 文件大小显示为 0，不能用 fs::copy，需要先读出内容再写入
+fn copy_file(from: &Path, to: &Path) -> bool {
+    let Ok(content) = std::fs::read(from) else { return false };
+    if let Some(parent) = to.parent()
+        && std::fs::create_dir_all(parent).is_err()
+    {
+        return false;
+    }
+    std::fs::write(to, content).is_ok()
+}
+
+// 以相同目标重建符号链接（目标不必存在）
+fn copy_link(from: &Path, to: &Path) -> bool {
+    let Ok(target) = std::fs::read_link(from) else { return false };
+    if let Some(parent) = to.parent()
+        && std::fs::create_dir_all(parent).is_err()
+    {
+        return false;
+    }
+    let _ = std::fs::remove_file(to);
+    std::os::unix::fs::symlink(target, to).is_ok()
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use crate::{source, util};
+
+/// 当前 cgroup 的 CPU 使用情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupCpu {
+    /// 相对 CPU 配额的使用率 (%)，没有配额时相对可用核心数
+    pub usage: f64,
+    /// cpu.max 限制的核心数，未限制时为空
+    #[serde(rename = "limitCores", default, skip_serializing_if = "Option::is_none")]
+    pub limit_cores: Option<f64>,
+    #[serde(rename = "usageUsec")]
+    pub usage_usec: u64,
+    #[serde(rename = "userUsec")]
+    pub user_usec: u64,
+    #[serde(rename = "systemUsec")]
+    pub system_usec: u64,
+    /// 被限流的周期数
+    #[serde(rename = "nrThrottled")]
+    pub nr_throttled: u64,
+    #[serde(rename = "throttledUsec")]
+    pub throttled_usec: u64,
+}
+
+/// 当前值与上限，上限为 max 时 limit 与 usage 为空
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupLimit {
+    pub current: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<f64>,
+}
+
+/// 单个块设备上的 cgroup IO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupIo {
+    /// 设备号，格式为 major:minor
+    pub device: String,
+    /// 读取速率 (MB/s)
+    #[serde(rename = "readRate")]
+    pub read_rate: f64,
+    /// 写入速率 (MB/s)
+    #[serde(rename = "writeRate")]
+    pub write_rate: f64,
+    #[serde(rename = "readIops")]
+    pub read_iops: f64,
+    #[serde(rename = "writeIops")]
+    pub write_iops: f64,
+}
+
+/// 采集程序自身所在 cgroup 的指标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupMetrics {
+    pub path: String,
+    pub cpu: CgroupCpu,
+    /// memory.current / memory.max（字节）
+    pub memory: CgroupLimit,
+    /// pids.current / pids.max
+    pub pids: CgroupLimit,
+    #[serde(default)]
+    pub io: Vec<CgroupIo>,
+}
+
+/// io.stat 中单个设备的累计计数
+#[derive(Debug, Clone, Copy, Default)]
+struct IoCounters {
+    rbytes: u64,
+    wbytes: u64,
+    rios: u64,
+    wios: u64,
+}
+
+/// cgroup 采样器，在多次采集之间保留 CPU 与 IO 计数
+#[derive(Debug, Default)]
+pub struct CgroupSampler {
+    prev: Option<(Instant, u64, HashMap<String, IoCounters>)>,
+}
+
+impl CgroupSampler {
+    /// 尚无基准数据时先记录一次计数
+    pub fn prime(&mut self) {
+        if self.prev.is_some() {
+            return;
+        }
+        if let Some(dir) = own_cgroup_dir() {
+            let usage = read_key_values(&dir.join("cpu.stat")).get("usage_usec").copied().unwrap_or(0);
+            self.prev = Some((Instant::now(), usage, read_io_stat(&dir)));
+        }
+    }
+
+    /// 采样当前 cgroup，非 cgroup v2 环境返回 None
+    pub fn sample(&mut self) -> Option<CgroupMetrics> {
+        let dir = own_cgroup_dir()?;
+        let now = Instant::now();
+
+        let cpu_stat = read_key_values(&dir.join("cpu.stat"));
+        let cpu_value = |key: &str| cpu_stat.get(key).copied().unwrap_or(0);
+        let usage_usec = cpu_value("usage_usec");
+        let limit_cores = read_cpu_max(&dir);
+        let io_counters = read_io_stat(&dir);
+
+        let mut cpu_usage = 0.0;
+        let mut io = Vec::new();
+        if let Some((prev_time, prev_usage, prev_io)) = &self.prev {
+            let elapsed = now.duration_since(*prev_time).as_secs_f64();
+            if elapsed > 0.0 {
+                let cores = limit_cores.unwrap_or_else(available_cores);
+                let used_secs = usage_usec.saturating_sub(*prev_usage) as f64 / 1_000_000.0;
+                cpu_usage = util::round1((used_secs / elapsed / cores * 100.0).min(100.0));
+                io = io_rates(prev_io, &io_counters, elapsed);
+            }
+        }
+        self.prev = Some((now, usage_usec, io_counters));
+
+        Some(CgroupMetrics {
+            path: display_path(&dir),
+            cpu: CgroupCpu {
+                usage: cpu_usage,
+                limit_cores,
+                usage_usec,
+                user_usec: cpu_value("user_usec"),
+                system_usec: cpu_value("system_usec"),
+                nr_throttled: cpu_value("nr_throttled"),
+                throttled_usec: cpu_value("throttled_usec"),
+            },
+            memory: read_limit(&dir, "memory.current", "memory.max"),
+            pids: read_limit(&dir, "pids.current", "pids.max"),
+            io,
+        })
+    }
+}
+
+/// 采集程序自身所在的 cgroup v2 目录
+pub fn own_cgroup_dir() -> Option<PathBuf> {
+    cgroup_dir_of("self")
+}
+
+/// 指定进程（pid 或 self）所在的 cgroup v2 目录
+pub fn cgroup_dir_of(pid: &str) -> Option<PathBuf> {
+    let root = cgroup2_root();
+    if !root.join("cgroup.controllers").exists() {
+        return None;
+    }
+    let content = source::current().read_proc(format!("{}/cgroup", pid)).ok()?;
+    // cgroup v2 只有一行: 0::/user.slice/user-1000.slice/session-2.scope
+    let path = content.lines().find_map(|line| line.strip_prefix("0::"))?;
+    let dir = root.join(path.trim_start_matches('/'));
+    dir.is_dir().then_some(dir)
+}
+
+/// cgroup v2 统一层级的挂载点
+fn cgroup2_root() -> PathBuf {
+    source::current().sys_path("fs/cgroup")
+}
+
+/// cgroup 目录在层级中的路径，例如 /user.slice/user-1000.slice
+pub fn display_path(dir: &Path) -> String {
+    dir.strip_prefix(cgroup2_root())
+        .map(|p| format!("/{}", p.display()))
+        .unwrap_or_else(|_| dir.display().to_string())
+}
+
+/// cgroup 目录及其所有祖先目录（不含根目录），限制对整个子树生效时需要逐级检查
+pub fn with_ancestors(dir: &Path) -> Vec<PathBuf> {
+    let root = cgroup2_root();
+    dir.ancestors()
+        .take_while(|ancestor| *ancestor != root && ancestor.starts_with(&root))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// 读取 current/max 一对文件，max 为 "max" 时视为不限制
+pub fn read_limit(dir: &Path, current_file: &str, max_file: &str) -> CgroupLimit {
+    let current = read_u64(&dir.join(current_file)).unwrap_or(0);
+    let limit = read_u64(&dir.join(max_file));
+    CgroupLimit {
+        current,
+        limit,
+        usage: limit.filter(|l| *l > 0).map(|l| util::round1(current as f64 / l as f64 * 100.0)),
+    }
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn read_key_values(path: &Path) -> HashMap<String, u64> {
+    std::fs::read_to_string(path)
+        .map(|content| {
+            content.lines()
+                .filter_map(|line| {
+                    let (key, value) = line.split_once(' ')?;
+                    Some((key.to_string(), value.trim().parse().ok()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// cpu.max 格式: "$MAX $PERIOD"，MAX 为 max 时不限制
+fn read_cpu_max(dir: &Path) -> Option<f64> {
+    let content = std::fs::read_to_string(dir.join("cpu.max")).ok()?;
+    let (quota, period) = content.trim().split_once(' ')?;
+    let quota = quota.parse::<f64>().ok()?;
+    let period = period.parse::<f64>().ok()?;
+    (period > 0.0).then(|| quota / period)
+}
+
+fn available_cores() -> f64 {
+    std::thread::available_parallelism().map(|n| n.get() as f64).unwrap_or(1.0)
+}
+
+// io.stat 格式: 8:0 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0
+fn read_io_stat(dir: &Path) -> HashMap<String, IoCounters> {
+    let Ok(content) = std::fs::read_to_string(dir.join("io.stat")) else {
+        return HashMap::new();
+    };
+
+    content.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let device = parts.next()?.to_string();
+            let mut counters = IoCounters::default();
+            for field in parts {
+                match field.split_once('=') {
+                    Some(("rbytes", v)) => counters.rbytes = v.parse().unwrap_or(0),
+                    Some(("wbytes", v)) => counters.wbytes = v.parse().unwrap_or(0),
+                    Some(("rios", v)) => counters.rios = v.parse().unwrap_or(0),
+                    Some(("wios", v)) => counters.wios = v.parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+            Some((device, counters))
+        })
+        .collect()
+}
+
+fn io_rates(prev: &HashMap<String, IoCounters>, now: &HashMap<String, IoCounters>, elapsed: f64) -> Vec<CgroupIo> {
+    let to_mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let mut result: Vec<CgroupIo> = now.iter()
+        .filter_map(|(device, current)| {
+            let before = prev.get(device)?;
+            // 计数回退说明 cgroup 被重建，跳过本次
+            if current.rbytes < before.rbytes || current.wbytes < before.wbytes {
+                return None;
+            }
+            Some(CgroupIo {
+                device: device.clone(),
+                read_rate: util::round1(to_mb(current.rbytes - before.rbytes) / elapsed),
+                write_rate: util::round1(to_mb(current.wbytes - before.wbytes) / elapsed),
+                read_iops: util::round1(current.rios.saturating_sub(before.rios) as f64 / elapsed),
+                write_iops: util::round1(current.wios.saturating_sub(before.wios) as f64 / elapsed),
+            })
+        })
+        .collect();
+    result.sort_by(|a, b| a.device.cmp(&b.device));
+    result
+}
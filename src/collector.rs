@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::metrics::{MetricsCollector, MetricsConfig};
+use crate::process::{ProcessCollector, ProcessConfig};
+
+/// 常驻采集上下文，保存各采集器在两次采集之间的状态
+///
+/// monitor 循环与 HTTP 服务共用同一个实例，速率与 CPU 使用率都相对上一次采集计算，
+/// 不必每次请求都重新等待基准数据
+pub struct Collector {
+    metrics: MetricsCollector,
+    processes: ProcessCollector,
+}
+
+/// 在 monitor 循环与 HTTP 服务之间共享的采集上下文
+pub type SharedCollector = Arc<Mutex<Collector>>;
+
+impl Collector {
+    pub fn new(metrics_config: MetricsConfig, process_config: ProcessConfig) -> Self {
+        Collector {
+            metrics: MetricsCollector::new(metrics_config),
+            processes: ProcessCollector::new(process_config),
+        }
+    }
+
+    pub fn shared(metrics_config: MetricsConfig, process_config: ProcessConfig) -> SharedCollector {
+        Arc::new(Mutex::new(Self::new(metrics_config, process_config)))
+    }
+
+    /// monitor 每轮采集的指标与进程数据，按输出的键名组织
+    ///
+    /// 进程只枚举一次，进程数据、线程耗尽预警与生命周期事件共用同一份结果
+    pub fn collect_tick(&mut self) -> serde_json::Map<String, serde_json::Value> {
+        let mut combined_data = serde_json::Map::new();
+
+        match self.metrics.collect() {
+            Ok(json_str) => {
+                if let Ok(val) = serde_json::from_str::<serde_json::Value>(&json_str) {
+                    combined_data.insert("metrics".to_string(), val);
+                } else {
+                    eprintln!("Error parsing metrics json");
+                }
+            },
+            Err(e) => eprintln!("Error collecting metrics: {}", e),
+        }
+
+        match serde_json::to_value(self.processes.collect_tick()) {
+            Ok(serde_json::Value::Object(process_data)) => combined_data.extend(process_data),
+            Ok(_) => eprintln!("Error parsing process json"),
+            Err(e) => eprintln!("Error collecting processes: {}", e),
+        }
+
+        combined_data
+    }
+
+    pub fn collect_metrics(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.metrics.collect()
+    }
+
+    pub fn collect_processes(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.processes.collect()
+    }
+
+    pub fn collect_process_tree(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.processes.collect_tree()
+    }
+
+    pub fn check_thread_limits(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.processes.check_thread_limits()
+    }
+
+    pub fn collect_top_io(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.processes.collect_top_io()
+    }
+}
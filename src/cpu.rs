@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use crate::{source, util};
+
+/// 一段采样间隔内的 CPU 时间占比（百分比）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CpuTimes {
+    pub usage: f64,
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    pub steal: f64,
+}
+
+/// 单个核心的 CPU 使用情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuCoreData {
+    pub core: u32,
+    #[serde(flatten)]
+    pub times: CpuTimes,
+}
+
+/// /proc/stat 中一行 cpu 计数（单位：jiffies）
+#[derive(Debug, Clone, Copy, Default)]
+struct CpuCounters {
+    user: u64,
+    nice: u64,
+    system: u64,
+    idle: u64,
+    iowait: u64,
+    irq: u64,
+    softirq: u64,
+    steal: u64,
+}
+
+impl CpuCounters {
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let value = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+        if fields.len() < 4 {
+            return None;
+        }
+        // guest/guest_nice 已经计入 user/nice，这里不再重复累加
+        Some(CpuCounters {
+            user: value(0),
+            nice: value(1),
+            system: value(2),
+            idle: value(3),
+            iowait: value(4),
+            irq: value(5),
+            softirq: value(6),
+            steal: value(7),
+        })
+    }
+
+    fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq + self.softirq + self.steal
+    }
+
+    /// 计算与上一次采样之间各项时间的占比
+    fn percent_since(&self, prev: &CpuCounters) -> CpuTimes {
+        let total = self.total().saturating_sub(prev.total());
+        if total == 0 {
+            return CpuTimes::default();
+        }
+        let pct = |now: u64, before: u64| util::round1(now.saturating_sub(before) as f64 * 100.0 / total as f64);
+        let idle = self.idle.saturating_sub(prev.idle) + self.iowait.saturating_sub(prev.iowait);
+        CpuTimes {
+            usage: util::round1(total.saturating_sub(idle) as f64 * 100.0 / total as f64),
+            user: pct(self.user, prev.user),
+            nice: pct(self.nice, prev.nice),
+            system: pct(self.system, prev.system),
+            iowait: pct(self.iowait, prev.iowait),
+            irq: pct(self.irq, prev.irq),
+            softirq: pct(self.softirq, prev.softirq),
+            steal: pct(self.steal, prev.steal),
+        }
+    }
+}
+
+/// 一次 /proc/stat 采样结果
+#[derive(Debug, Clone, Default)]
+pub struct CpuStatSnapshot {
+    total: CpuCounters,
+    cores: Vec<(u32, CpuCounters)>,
+}
+
+/// 读取 /proc/stat 中的汇总与各核心计数
+pub fn read_cpu_stat() -> Option<CpuStatSnapshot> {
+    let content = source::current().read_proc("stat").ok()?;
+    parse_cpu_stat(&content)
+}
+
+fn parse_cpu_stat(content: &str) -> Option<CpuStatSnapshot> {
+    let mut snapshot = CpuStatSnapshot::default();
+    let mut found_total = false;
+
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let Some(label) = parts.next() else { continue };
+        if !label.starts_with("cpu") {
+            continue;
+        }
+        let fields: Vec<&str> = parts.collect();
+        let Some(counters) = CpuCounters::parse(&fields) else { continue };
+
+        if label == "cpu" {
+            snapshot.total = counters;
+            found_total = true;
+        } else if let Ok(core) = label[3..].parse::<u32>() {
+            snapshot.cores.push((core, counters));
+        }
+    }
+
+    found_total.then_some(snapshot)
+}
+
+/// 计算自系统启动以来的平均使用率，用于只有一次采样的目录快照
+pub fn usage_since_boot(now: &CpuStatSnapshot) -> (CpuTimes, Vec<CpuCoreData>) {
+    let boot = CpuStatSnapshot {
+        total: CpuCounters::default(),
+        cores: now.cores.iter().map(|(core, _)| (*core, CpuCounters::default())).collect(),
+    };
+    usage_between(&boot, now)
+}
+
+/// 根据前后两次采样计算汇总与每个核心的使用率
+///
+/// 核心在两次采样之间上下线时，只输出两次都存在的核心
+pub fn usage_between(prev: &CpuStatSnapshot, now: &CpuStatSnapshot) -> (CpuTimes, Vec<CpuCoreData>) {
+    let total = now.total.percent_since(&prev.total);
+    let cores = now.cores.iter()
+        .filter_map(|(core, counters)| {
+            let (_, before) = prev.cores.iter().find(|(c, _)| c == core)?;
+            Some(CpuCoreData {
+                core: *core,
+                times: counters.percent_since(before),
+            })
+        })
+        .collect();
+    (total, cores)
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use crate::{source, util};
+
+/// 单个整盘设备在采样间隔内的 IO 情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskIoData {
+    pub device: String,
+    /// 读取速率 (MB/s)
+    #[serde(rename = "readRate")]
+    pub read_rate: f64,
+    /// 写入速率 (MB/s)
+    #[serde(rename = "writeRate")]
+    pub write_rate: f64,
+    #[serde(rename = "readIops")]
+    pub read_iops: f64,
+    #[serde(rename = "writeIops")]
+    pub write_iops: f64,
+    /// 平均每个 IO 的等待时间 (ms)
+    #[serde(rename = "awaitMs")]
+    pub await_ms: f64,
+    /// 设备繁忙时间占比 (%)
+    pub utilization: f64,
+}
+
+/// /proc/diskstats 中单个设备的累计计数
+#[derive(Debug, Clone, Copy, Default)]
+struct DiskCounters {
+    reads: u64,
+    sectors_read: u64,
+    read_ms: u64,
+    writes: u64,
+    sectors_written: u64,
+    write_ms: u64,
+    io_ticks: u64,
+}
+
+impl DiskCounters {
+    fn parse(parts: &[&str]) -> Option<Self> {
+        let value = |i: usize| parts.get(i)?.parse::<u64>().ok();
+        Some(DiskCounters {
+            reads: value(3)?,
+            sectors_read: value(5)?,
+            read_ms: value(6)?,
+            writes: value(7)?,
+            sectors_written: value(9)?,
+            write_ms: value(10)?,
+            io_ticks: value(12)?,
+        })
+    }
+
+    /// 任一计数回退说明设备被重新挂载、计数溢出或系统已重启
+    fn is_reset_since(&self, prev: &DiskCounters) -> bool {
+        self.reads < prev.reads
+            || self.sectors_read < prev.sectors_read
+            || self.read_ms < prev.read_ms
+            || self.writes < prev.writes
+            || self.sectors_written < prev.sectors_written
+            || self.write_ms < prev.write_ms
+            || self.io_ticks < prev.io_ticks
+    }
+}
+
+/// 磁盘 IO 采样器，在多次采集之间保留上一次的计数
+#[derive(Debug, Default)]
+pub struct DiskIoSampler {
+    prev: Option<(Instant, HashMap<String, DiskCounters>)>,
+}
+
+impl DiskIoSampler {
+    /// 尚无基准数据时先记录一次计数
+    pub fn prime(&mut self) {
+        if self.prev.is_none() {
+            self.prev = Some((Instant::now(), read_disk_counters()));
+        }
+    }
+
+    /// 采样并返回与上次采样之间每个整盘设备的速率
+    ///
+    /// 首次调用或设备计数发生回退时，相应设备没有输出
+    pub fn sample(&mut self) -> Vec<DiskIoData> {
+        let now = Instant::now();
+        let counters = read_disk_counters();
+        let mut result = Vec::new();
+
+        if let Some((prev_time, prev_counters)) = &self.prev {
+            let elapsed = now.duration_since(*prev_time).as_secs_f64();
+            if elapsed > 0.0 {
+                for (device, current) in &counters {
+                    let Some(prev) = prev_counters.get(device) else { continue };
+                    if current.is_reset_since(prev) {
+                        continue;
+                    }
+                    result.push(rates_between(device, prev, current, elapsed));
+                }
+            }
+        }
+
+        result.sort_by(|a, b| a.device.cmp(&b.device));
+        self.prev = Some((now, counters));
+        result
+    }
+
+    /// 返回自系统启动以来每个整盘设备的平均速率，用于只有一次采样的目录快照
+    pub fn sample_since_boot(&self, uptime: f64) -> Vec<DiskIoData> {
+        if uptime <= 0.0 {
+            return Vec::new();
+        }
+        let mut result: Vec<DiskIoData> = read_disk_counters().iter()
+            .map(|(device, current)| rates_between(device, &DiskCounters::default(), current, uptime))
+            .collect();
+        result.sort_by(|a, b| a.device.cmp(&b.device));
+        result
+    }
+}
+
+fn rates_between(device: &str, prev: &DiskCounters, now: &DiskCounters, elapsed: f64) -> DiskIoData {
+    // 每个扇区固定按 512 字节计算
+    let to_mb = |sectors: u64| sectors as f64 * 512.0 / (1024.0 * 1024.0);
+    let reads = now.reads - prev.reads;
+    let writes = now.writes - prev.writes;
+    let io_ms = (now.read_ms - prev.read_ms) + (now.write_ms - prev.write_ms);
+    let await_ms = if reads + writes > 0 {
+        io_ms as f64 / (reads + writes) as f64
+    } else {
+        0.0
+    };
+    let utilization = ((now.io_ticks - prev.io_ticks) as f64 / (elapsed * 1000.0) * 100.0).min(100.0);
+
+    DiskIoData {
+        device: device.to_string(),
+        read_rate: util::round1(to_mb(now.sectors_read - prev.sectors_read) / elapsed),
+        write_rate: util::round1(to_mb(now.sectors_written - prev.sectors_written) / elapsed),
+        read_iops: util::round1(reads as f64 / elapsed),
+        write_iops: util::round1(writes as f64 / elapsed),
+        await_ms: util::round1(await_ms),
+        utilization: util::round1(utilization),
+    }
+}
+
+// 读取所有整盘设备的累计计数
+fn read_disk_counters() -> HashMap<String, DiskCounters> {
+    let Ok(content) = source::current().read_proc("diskstats") else {
+        return HashMap::new();
+    };
+
+    content.lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let device = *parts.get(2)?;
+            if !is_whole_disk(device) {
+                return None;
+            }
+            Some((device.to_string(), DiskCounters::parse(&parts)?))
+        })
+        .collect()
+}
+
+// 只统计带有物理设备的整盘（排除分区、loop、dm、md、zram 等）
+fn is_whole_disk(device: &str) -> bool {
+    let sys_block = source::current().sys_path("block");
+    if sys_block.is_dir() {
+        return sys_block.join(device).join("device").exists();
+    }
+    // 没有 sysfs 时退回到按名称判断
+    !device.starts_with("loop")
+        && !device.starts_with("ram")
+        && !device.chars().last().unwrap_or('0').is_ascii_digit()
+}
@@ -10,16 +10,23 @@ pub fn collect_dmesg(since_seconds: Option<f64>) -> Result<(String, Option<f64>)
     {
         use std::process::Command;
         
-        // 在 Linux 上执行 dmesg 命令
-        let output = Command::new("dmesg")
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(format!("dmesg command failed: {}", 
-                String::from_utf8_lossy(&output.stderr)).into());
-        }
+        let source = crate::source::current();
+        let stdout = if source.is_live() {
+            // 在 Linux 上执行 dmesg 命令
+            let output = Command::new("dmesg")
+                .output()?;
+            
+            if !output.status.success() {
+                return Err(format!("dmesg command failed: {}", 
+                    String::from_utf8_lossy(&output.stderr)).into());
+            }
+            
+            String::from_utf8_lossy(&output.stdout).to_string()
+        } else {
+            // 目录快照中 dmesg 输出由 capture 子命令保存在 procfs 根目录下
+            source.read_proc("dmesg").unwrap_or_default()
+        };
         
-        let stdout = String::from_utf8_lossy(&output.stdout);
         let mut last_timestamp = since_seconds;
         
         if let Some(since) = since_seconds {
@@ -41,7 +48,7 @@ pub fn collect_dmesg(since_seconds: Option<f64>) -> Result<(String, Option<f64>)
                     last_timestamp = Some(ts);
                 }
             }
-            Ok((stdout.to_string(), last_timestamp))
+            Ok((stdout, last_timestamp))
         }
     }
     
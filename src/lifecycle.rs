@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 进程生命周期事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessEvent {
+    /// process_started、process_exited 或 process_restarted
+    pub event: String,
+    /// 启动与重启事件为进程启动时间，退出事件为检测到退出的采集时间（毫秒）
+    pub timestamp: u64,
+    pub pid: u32,
+    /// 重启前的 pid
+    #[serde(rename = "previousPid", default, skip_serializing_if = "Option::is_none")]
+    pub previous_pid: Option<u32>,
+    /// 退出前最后一次采集到该进程的时间（毫秒）
+    #[serde(rename = "lastSeen", default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<u64>,
+    pub name: String,
+    pub cmdline: String,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+}
+
+/// 本次采集到的进程
+pub struct ObservedProcess {
+    pub pid: u32,
+    /// 进程启动时间，与 pid 一起唯一标识进程
+    pub start_time: u64,
+    pub name: String,
+    pub user_name: String,
+}
+
+/// 已跟踪的进程
+struct TrackedProcess {
+    name: String,
+    cmdline: String,
+    user_name: String,
+    /// 启动时间戳（毫秒）
+    started_at: u64,
+}
+
+/// 对比相邻两次采集的进程集合，生成启动、退出与重启事件
+///
+/// 重启按进程名与命令行匹配：同一次对比中退出的进程与新启动的进程名称、命令行相同即视为重启
+#[derive(Default)]
+pub struct LifecycleTracker {
+    known: Option<HashMap<(u32, u64), TrackedProcess>>,
+    last_seen: u64,
+}
+
+impl LifecycleTracker {
+    /// 记录本次采集的进程集合并返回与上次相比的事件，首次调用只记录基准
+    ///
+    /// # Arguments
+    /// * `details` - 读取新进程的命令行与启动时间戳（毫秒），只对新出现的进程调用
+    pub fn update(
+        &mut self,
+        timestamp: u64,
+        observed: Vec<ObservedProcess>,
+        details: impl Fn(&ObservedProcess) -> (String, u64),
+    ) -> Vec<ProcessEvent> {
+        let mut previous = self.known.take();
+        let mut current = HashMap::new();
+        let mut started = Vec::new();
+
+        for process in observed {
+            let key = (process.pid, process.start_time);
+            let tracked = match previous.as_mut().and_then(|known| known.remove(&key)) {
+                Some(tracked) => tracked,
+                None => {
+                    let (cmdline, started_at) = details(&process);
+                    if previous.is_some() {
+                        started.push(key);
+                    }
+                    TrackedProcess {
+                        name: process.name,
+                        cmdline,
+                        user_name: process.user_name,
+                        started_at,
+                    }
+                }
+            };
+            current.insert(key, tracked);
+        }
+
+        // 上次存在而本次不存在的进程已退出
+        let mut exited: Vec<(u32, TrackedProcess)> = previous
+            .map(|known| known.into_iter().map(|((pid, _), tracked)| (pid, tracked)).collect())
+            .unwrap_or_default();
+        exited.sort_by_key(|(pid, _)| *pid);
+
+        let mut events = Vec::new();
+        for key in started {
+            let Some(tracked) = current.get(&key) else { continue };
+            let restarted_from = exited.iter()
+                .position(|(_, old)| old.name == tracked.name && old.cmdline == tracked.cmdline)
+                .map(|index| exited.remove(index).0);
+            events.push(ProcessEvent {
+                event: if restarted_from.is_some() { "process_restarted" } else { "process_started" }.to_string(),
+                timestamp: tracked.started_at,
+                pid: key.0,
+                previous_pid: restarted_from,
+                last_seen: None,
+                name: tracked.name.clone(),
+                cmdline: tracked.cmdline.clone(),
+                user_name: tracked.user_name.clone(),
+            });
+        }
+        for (pid, tracked) in exited {
+            events.push(ProcessEvent {
+                event: "process_exited".to_string(),
+                timestamp,
+                pid,
+                previous_pid: None,
+                last_seen: Some(self.last_seen),
+                name: tracked.name,
+                cmdline: tracked.cmdline,
+                user_name: tracked.user_name,
+            });
+        }
+        events.sort_by_key(|event| event.timestamp);
+
+        self.known = Some(current);
+        self.last_seen = timestamp;
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observed(pid: u32, start_time: u64, name: &str) -> ObservedProcess {
+        ObservedProcess {
+            pid,
+            start_time,
+            name: name.to_string(),
+            user_name: "root".to_string(),
+        }
+    }
+
+    // 命令行取进程名，启动时间戳为启动 tick 的 10 倍
+    fn details(process: &ObservedProcess) -> (String, u64) {
+        (format!("/usr/bin/{}", process.name), process.start_time * 10)
+    }
+
+    #[test]
+    fn first_update_only_records_baseline() {
+        let mut tracker = LifecycleTracker::default();
+        let events = tracker.update(1000, vec![observed(1, 5, "init"), observed(10, 50, "app")], details);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn reports_started_process() {
+        let mut tracker = LifecycleTracker::default();
+        tracker.update(1000, vec![observed(1, 5, "init")], details);
+
+        let events = tracker.update(2000, vec![observed(1, 5, "init"), observed(20, 150, "worker")], details);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "process_started");
+        assert_eq!(events[0].pid, 20);
+        assert_eq!(events[0].timestamp, 1500);
+        assert_eq!(events[0].cmdline, "/usr/bin/worker");
+        assert_eq!(events[0].previous_pid, None);
+    }
+
+    #[test]
+    fn reports_exited_process_with_last_seen() {
+        let mut tracker = LifecycleTracker::default();
+        tracker.update(1000, vec![observed(1, 5, "init"), observed(10, 50, "app")], details);
+        tracker.update(2000, vec![observed(1, 5, "init"), observed(10, 50, "app")], details);
+
+        let events = tracker.update(3000, vec![observed(1, 5, "init")], details);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "process_exited");
+        assert_eq!(events[0].pid, 10);
+        assert_eq!(events[0].timestamp, 3000);
+        assert_eq!(events[0].last_seen, Some(2000));
+    }
+
+    #[test]
+    fn matches_restart_by_name_and_cmdline() {
+        let mut tracker = LifecycleTracker::default();
+        tracker.update(1000, vec![observed(10, 50, "app"), observed(11, 60, "other")], details);
+
+        let events = tracker.update(2000, vec![observed(11, 60, "other"), observed(30, 180, "app")], details);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "process_restarted");
+        assert_eq!(events[0].pid, 30);
+        assert_eq!(events[0].previous_pid, Some(10));
+        assert_eq!(events[0].timestamp, 1800);
+    }
+
+    #[test]
+    fn reused_pid_with_new_start_time_is_a_new_process() {
+        let mut tracker = LifecycleTracker::default();
+        tracker.update(1000, vec![observed(10, 50, "app")], details);
+
+        let mut events = tracker.update(2000, vec![observed(10, 190, "cron")], details);
+        events.sort_by(|a, b| a.event.cmp(&b.event));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event, "process_exited");
+        assert_eq!(events[0].name, "app");
+        assert_eq!(events[0].pid, 10);
+        assert_eq!(events[1].event, "process_started");
+        assert_eq!(events[1].name, "cron");
+        assert_eq!(events[1].pid, 10);
+        assert_eq!(events[1].timestamp, 1900);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use crate::source;
+
+/// /proc/loadavg 中的平均负载
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadAverage {
+    pub load1: f64,
+    pub load5: f64,
+    pub load15: f64,
+    /// 当前可运行的调度实体数
+    pub runnable: u32,
+    /// 系统中的调度实体总数
+    pub total: u32,
+}
+
+/// 一类资源在 10/60/300 秒窗口内的阻塞时间占比 (%)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PressureValues {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    /// 累计阻塞时间（微秒）
+    pub total: u64,
+}
+
+/// 单个资源的 PSI 数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PressureData {
+    /// 至少有一个任务因该资源阻塞
+    pub some: PressureValues,
+    /// 所有非空闲任务都因该资源阻塞，旧内核的 cpu 没有此项
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full: Option<PressureValues>,
+}
+
+/// cpu/memory/io 的 PSI 数据，内核未开启 PSI 时各项为空
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PressureStall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<PressureData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<PressureData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io: Option<PressureData>,
+}
+
+/// 读取 /proc/loadavg
+pub fn read_load_average() -> Option<LoadAverage> {
+    let content = source::current().read_proc("loadavg").ok()?;
+    // 格式: 0.52 0.58 0.59 2/1234 56789
+    let parts: Vec<&str> = content.split_whitespace().collect();
+    let (runnable, total) = parts.get(3)?.split_once('/')?;
+    Some(LoadAverage {
+        load1: parts.first()?.parse().ok()?,
+        load5: parts.get(1)?.parse().ok()?,
+        load15: parts.get(2)?.parse().ok()?,
+        runnable: runnable.parse().ok()?,
+        total: total.parse().ok()?,
+    })
+}
+
+/// 读取 /proc/pressure 下的 PSI 数据，全部不可用时返回 None
+pub fn read_pressure() -> Option<PressureStall> {
+    let pressure = PressureStall {
+        cpu: read_pressure_file("cpu"),
+        memory: read_pressure_file("memory"),
+        io: read_pressure_file("io"),
+    };
+    if pressure.cpu.is_none() && pressure.memory.is_none() && pressure.io.is_none() {
+        return None;
+    }
+    Some(pressure)
+}
+
+fn read_pressure_file(resource: &str) -> Option<PressureData> {
+    let content = source::current().read_proc(format!("pressure/{}", resource)).ok()?;
+    let mut some = None;
+    let mut full = None;
+
+    // 格式: some avg10=0.00 avg60=0.00 avg300=0.00 total=0
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("some") => some = parse_pressure_values(parts),
+            Some("full") => full = parse_pressure_values(parts),
+            _ => {}
+        }
+    }
+
+    Some(PressureData { some: some?, full })
+}
+
+fn parse_pressure_values<'a>(fields: impl Iterator<Item = &'a str>) -> Option<PressureValues> {
+    let (mut avg10, mut avg60, mut avg300, mut total) = (None, None, None, None);
+    for field in fields {
+        match field.split_once('=') {
+            Some(("avg10", v)) => avg10 = v.parse().ok(),
+            Some(("avg60", v)) => avg60 = v.parse().ok(),
+            Some(("avg300", v)) => avg300 = v.parse().ok(),
+            Some(("total", v)) => total = v.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(PressureValues {
+        avg10: avg10?,
+        avg60: avg60?,
+        avg300: avg300?,
+        total: total?,
+    })
+}
@@ -1,18 +1,36 @@
 use axum::{
     Json, Router,
+    extract::{FromRef, Path, State},
     routing::{get, post},
 };
 use tower_http::cors::CorsLayer;
-use clap::{Parser, Subcommand};
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc};
 use tokio::{net::TcpListener, sync::Mutex};
 
+mod capture;
+mod cgroup;
+mod collector;
+mod cpu;
+mod diskio;
 mod dmesg;
+mod lifecycle;
+mod load;
+mod meminfo;
 mod metrics;
+mod mounts;
+mod network;
 mod process;
+mod ranking;
+mod resources;
+mod sensors;
+mod source;
+mod thread_limits;
+mod trend;
 mod util;
 mod socket_shell;
 use socket_shell::{Sessions, websocket_handler};
+use collector::{Collector, SharedCollector};
 
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -27,6 +45,16 @@ struct Cli {
     #[arg(long, global = true)]
     server: Option<u16>,
 
+    /// procfs 根目录，可指向 capture 录制的快照（默认 /proc）
+    ///
+    /// 快照只有一次采样，CPU、磁盘与网络速率为自启动以来的平均值，挂载点使用录制时的 statvfs 结果
+    #[arg(long, global = true, value_name = "DIR")]
+    proc_root: Option<PathBuf>,
+
+    /// sysfs 根目录，可指向 capture 录制的快照（默认 /sys）
+    #[arg(long, global = true, value_name = "DIR")]
+    sys_root: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -35,12 +63,25 @@ struct Cli {
 enum Commands {
     /// 收集并输出进程信息
     Process {
-        /// 检查线程数最多的进程
+        /// 检查线程数最多的进程，配合 --rank-by 输出按线程、CPU、内存、fd、IO 或上下文切换排名的前 N 个进程
         #[arg(long)]
         check: bool,
+        /// 以进程树输出，附带各子树的 CPU、内存与线程合计
+        #[arg(long, conflicts_with = "check")]
+        tree: bool,
+        /// 检查线程数是否接近 threads-max、pid_max、cgroup pids.max 与 RLIMIT_NPROC
+        #[arg(long, conflicts_with_all = ["check", "tree"])]
+        thread_limits: bool,
+        #[command(flatten)]
+        ranking: ranking::RankingConfig,
+        #[command(flatten)]
+        config: process::ProcessConfig,
     },
     /// 收集并输出系统指标信息
-    Metrics,
+    Metrics {
+        #[command(flatten)]
+        config: metrics::MetricsConfig,
+    },
     /// 收集并输出 dmesg 日志
     Dmesg {
         /// 只获取此时间之后的日志（启动后秒数，例如：4.5）
@@ -55,20 +96,47 @@ enum Commands {
         /// 间隔秒数
         #[arg(long)]
         sec: Option<u64>,
+        #[command(flatten)]
+        metrics_config: metrics::MetricsConfig,
+        #[command(flatten)]
+        process_config: process::ProcessConfig,
     },
     /// 测试模式，使用 data.json 作为数据源
     Test,
+    /// 录制 procfs/sysfs 快照，供 --proc-root/--sys-root 回放
+    Capture {
+        /// 快照输出目录
+        #[arg(long, value_name = "DIR")]
+        output: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    if let Some(Commands::Process { config, .. } | Commands::Monitor { process_config: config, .. }) = &cli.command
+        && let Err(message) = config.thread_limits.validate()
+    {
+        Cli::command().error(clap::error::ErrorKind::ArgumentConflict, message).exit();
+    }
+    source::init(source::Source::new(cli.proc_root.clone(), cli.sys_root.clone()));
 
     let sessions: Sessions = Arc::new(Mutex::new(HashMap::new()));
 
+    // monitor 与 HTTP 服务共用同一个采集上下文，采集配置取自 monitor 参数
+    let (metrics_config, process_config) = match &cli.command {
+        Some(Commands::Monitor { metrics_config, process_config, .. }) => (metrics_config.clone(), process_config.clone()),
+        _ => Default::default(),
+    };
+    let collector = Collector::shared(metrics_config, process_config);
+
     if let Some(port) = cli.server {
+        let state = AppState {
+            sessions,
+            collector: collector.clone(),
+        };
         tokio::spawn(async move {
-            if let Err(e) = start_server(port, sessions).await {
+            if let Err(e) = start_server(port, state).await {
                 eprintln!("Server error: {}", e);
             }
         });
@@ -76,24 +144,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     if let Some(command) = cli.command {
         match command {
-            Commands::Process { check } => {
+            Commands::Process { check, tree, thread_limits, ranking, config } => {
                 if check {
-                    let json = process::check_max_threads_process()?;
+                    let output = process::check_processes(config, ranking)?;
+                    println!("{}", output);
+                } else if tree {
+                    let json = process::collect_process_tree(config)?;
+                    println!("{}", json);
+                } else if thread_limits {
+                    let json = process::check_thread_limits(config)?;
                     println!("{}", json);
                 } else {
-                    let json = process::collect_processes()?;
+                    let json = process::collect_processes(config)?;
                     println!("{}", json);
                 }
             }
-            Commands::Metrics => {
-                let json = metrics::collect_metrics()?;
+            Commands::Metrics { config } => {
+                let json = metrics::collect_metrics(config)?;
                 println!("{}", json);
             }
             Commands::Dmesg { since } => {
                 let (json, _) = dmesg::collect_dmesg(since)?;
                 println!("{}", json);
             }
-            Commands::Monitor { min, sec } => {
+            Commands::Monitor { min, sec, .. } => {
                 let interval_secs = min.unwrap_or(0) * 60 + sec.unwrap_or(0);
                 if interval_secs == 0 {
                     return Err("Please specify an interval using --min or --sec".into());
@@ -102,31 +176,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let mut last_dmesg_time: Option<f64> = None;
 
                 loop {
-                    let mut combined_data = serde_json::Map::new();
-
-                    match metrics::collect_metrics() {
-                        Ok(json_str) => {
-                            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&json_str) {
-                                combined_data.insert("metrics".to_string(), val);
-                            } else {
-                                eprintln!("Error parsing metrics json");
-                            }
-                        },
-                        Err(e) => eprintln!("Error collecting metrics: {}", e),
-                    }
-
-                    match process::collect_processes() {
-                        Ok(json_str) => {
-                            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&json_str) {
-                                combined_data.insert("process".to_string(), val);
-                            } else {
-                                eprintln!("Error parsing process json");
-                            }
-                        },
-                        Err(e) => eprintln!("Error collecting processes: {}", e),
-                    }
+                    // 采集会读取大量 procfs 文件并执行 dmesg 命令，放到阻塞线程池中执行
+                    let tick_collector = collector.clone();
+                    let since = last_dmesg_time;
+                    let tick = tokio::task::spawn_blocking(move || {
+                        let combined_data = tick_collector.blocking_lock().collect_tick();
+                        let dmesg = dmesg::collect_dmesg(since).map_err(|e| e.to_string());
+                        (combined_data, dmesg)
+                    })
+                    .await;
+                    let (mut combined_data, dmesg) = match tick {
+                        Ok(tick) => tick,
+                        Err(e) => {
+                            eprintln!("Error collecting data: {}", e);
+                            tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+                            continue;
+                        }
+                    };
 
-                    match dmesg::collect_dmesg(last_dmesg_time) {
+                    match dmesg {
                         Ok((dmesg_str, new_last_time)) => {
                             combined_data.insert("dmesg".to_string(), serde_json::Value::String(dmesg_str));
                             
@@ -148,6 +216,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 TEST_MODE.store(true, Ordering::SeqCst);
                 println!("Test mode enabled, using data.json as data source");
             }
+            Commands::Capture { output } => {
+                let count = capture::capture_snapshot(&output)?;
+                println!("Captured {} files into {}", count, output.display());
+            }
         }
     } else if cli.server.is_none(){
         use clap::CommandFactory;
@@ -162,11 +234,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn start_server(port: u16, sessions: Sessions) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// HTTP 服务共享状态
+#[derive(Clone)]
+struct AppState {
+    sessions: Sessions,
+    collector: SharedCollector,
+}
+
+impl FromRef<AppState> for Sessions {
+    fn from_ref(state: &AppState) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedCollector {
+    fn from_ref(state: &AppState) -> Self {
+        state.collector.clone()
+    }
+}
+
+async fn start_server(port: u16, state: AppState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let app = Router::new()
         .route("/api/getAllData", post(get_all_data).get(get_all_data))
+        .route("/api/metrics", get(get_metrics))
+        .route("/api/processes", get(get_processes))
+        .route("/api/processTree", get(get_process_tree))
+        .route("/api/threadLimits", get(get_thread_limits))
+        .route("/api/topIo", get(get_top_io))
+        .route("/api/threadStacks/:pid", get(get_thread_stacks))
         .route("/ws/terminal", get(websocket_handler))
-        .with_state(sessions)
+        .with_state(state)
         .layer(CorsLayer::permissive());
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     println!("Server listening on {}", addr);
@@ -203,3 +300,68 @@ async fn get_all_data() -> Json<serde_json::Value> {
     }
     Json(json_value)
 }
+
+/// 使用共享采集上下文采集指标
+async fn get_metrics(State(collector): State<SharedCollector>) -> Json<serde_json::Value> {
+    collect_json(collector, Collector::collect_metrics).await
+}
+
+/// 使用共享采集上下文采集进程信息
+async fn get_processes(State(collector): State<SharedCollector>) -> Json<serde_json::Value> {
+    collect_json(collector, Collector::collect_processes).await
+}
+
+/// 使用共享采集上下文构建进程树
+async fn get_process_tree(State(collector): State<SharedCollector>) -> Json<serde_json::Value> {
+    collect_json(collector, Collector::collect_process_tree).await
+}
+
+/// 使用共享采集上下文检查线程耗尽风险
+async fn get_thread_limits(State(collector): State<SharedCollector>) -> Json<serde_json::Value> {
+    collect_json(collector, Collector::check_thread_limits).await
+}
+
+/// 使用共享采集上下文统计磁盘 IO 最高的进程
+async fn get_top_io(State(collector): State<SharedCollector>) -> Json<serde_json::Value> {
+    collect_json(collector, Collector::collect_top_io).await
+}
+
+/// 导出指定进程所有线程的内核栈
+async fn get_thread_stacks(Path(pid): Path<u32>) -> Json<serde_json::Value> {
+    let result = tokio::task::spawn_blocking(move || {
+        process::dump_thread_stacks(pid).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(json_str)) => Json(serde_json::from_str(&json_str).unwrap_or(serde_json::json!({}))),
+        Ok(Err(e)) => Json(serde_json::json!({"error": e})),
+        Err(e) => {
+            eprintln!("Error dumping thread stacks: {}", e);
+            Json(serde_json::json!({"error": "Thread stack task failed"}))
+        }
+    }
+}
+
+// 采集会读取大量 procfs 文件（首次还需等待基准数据），放到阻塞线程池中执行
+async fn collect_json(
+    collector: SharedCollector,
+    collect: fn(&mut Collector) -> Result<String, Box<dyn std::error::Error>>,
+) -> Json<serde_json::Value> {
+    let result = tokio::task::spawn_blocking(move || {
+        collect(&mut collector.blocking_lock()).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(json_str)) => Json(serde_json::from_str(&json_str).unwrap_or(serde_json::json!([]))),
+        Ok(Err(e)) => {
+            eprintln!("Error collecting data: {}", e);
+            Json(serde_json::json!({"error": e}))
+        }
+        Err(e) => {
+            eprintln!("Error collecting data: {}", e);
+            Json(serde_json::json!({"error": "Collector task failed"}))
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::{source, util};
+
+/// /proc/meminfo 中的内存明细（单位：字节）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryDetail {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub buffers: u64,
+    pub cached: u64,
+    pub shmem: u64,
+    pub slab: u64,
+    /// slab 中可回收的部分
+    #[serde(rename = "slabReclaimable")]
+    pub slab_reclaimable: u64,
+    pub dirty: u64,
+    pub writeback: u64,
+    #[serde(rename = "swapTotal")]
+    pub swap_total: u64,
+    #[serde(rename = "swapFree")]
+    pub swap_free: u64,
+    #[serde(rename = "swapCached")]
+    pub swap_cached: u64,
+    /// 交换分区使用率 (%)
+    #[serde(rename = "swapUsage")]
+    pub swap_usage: f64,
+    #[serde(rename = "hugePagesTotal")]
+    pub huge_pages_total: u64,
+    #[serde(rename = "hugePagesFree")]
+    pub huge_pages_free: u64,
+    /// 单个大页的大小（字节）
+    #[serde(rename = "hugePageSize")]
+    pub huge_page_size: u64,
+}
+
+/// 读取 /proc/meminfo
+pub fn read_memory_detail() -> Option<MemoryDetail> {
+    let content = source::current().read_proc("meminfo").ok()?;
+
+    // 格式: MemTotal:       16314368 kB，HugePages_* 没有单位
+    let values: HashMap<&str, u64> = content.lines()
+        .filter_map(|line| {
+            let (key, rest) = line.split_once(':')?;
+            let mut parts = rest.split_whitespace();
+            let value = parts.next()?.parse::<u64>().ok()?;
+            let value = if parts.next() == Some("kB") { value * 1024 } else { value };
+            Some((key, value))
+        })
+        .collect();
+
+    let get = |key: &str| values.get(key).copied().unwrap_or(0);
+    let total = *values.get("MemTotal")?;
+    let swap_total = get("SwapTotal");
+    let swap_free = get("SwapFree");
+    let swap_used = swap_total.saturating_sub(swap_free);
+
+    Some(MemoryDetail {
+        total,
+        free: get("MemFree"),
+        // 3.14 之前的内核没有 MemAvailable
+        available: values.get("MemAvailable").copied()
+            .unwrap_or_else(|| get("MemFree") + get("Buffers") + get("Cached")),
+        buffers: get("Buffers"),
+        cached: get("Cached"),
+        shmem: get("Shmem"),
+        slab: get("Slab"),
+        slab_reclaimable: get("SReclaimable"),
+        dirty: get("Dirty"),
+        writeback: get("Writeback"),
+        swap_total,
+        swap_free,
+        swap_cached: get("SwapCached"),
+        swap_usage: if swap_total > 0 {
+            util::round1(swap_used as f64 / swap_total as f64 * 100.0)
+        } else {
+            0.0
+        },
+        huge_pages_total: get("HugePages_Total"),
+        huge_pages_free: get("HugePages_Free"),
+        huge_page_size: get("Hugepagesize"),
+    })
+}
@@ -1,7 +1,16 @@
+use clap::Args;
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
-use sysinfo::{System, Disks, Networks};
-use crate::util;
+use sysinfo::System;
+use crate::cgroup::{CgroupMetrics, CgroupSampler};
+use crate::cpu::{self, CpuCoreData, CpuStatSnapshot, CpuTimes};
+use crate::diskio::{DiskIoData, DiskIoSampler};
+use crate::load::{self, LoadAverage, PressureStall};
+use crate::meminfo::{self, MemoryDetail};
+use crate::mounts::{self, MountData, MountFilter};
+use crate::sensors::{self, SensorData};
+use crate::network::{InterfaceFilter, NetInterfaceData, NetworkSampler};
+use crate::{source, util};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MetricsData {
@@ -10,139 +19,278 @@ pub struct MetricsData {
     pub timestamp: u64,
     #[serde(rename = "cpuUsage")]
     pub cpu_usage: f64,
+    #[serde(rename = "cpuBreakdown", default, skip_serializing_if = "Option::is_none")]
+    pub cpu_breakdown: Option<CpuTimes>,
+    #[serde(rename = "cpuCores", default)]
+    pub cpu_cores: Vec<CpuCoreData>,
+    #[serde(rename = "loadAverage", default, skip_serializing_if = "Option::is_none")]
+    pub load_average: Option<LoadAverage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<PressureStall>,
     #[serde(rename = "memoryUsage")]
     pub memory_usage: f64,
+    #[serde(rename = "memoryDetail", default, skip_serializing_if = "Option::is_none")]
+    pub memory_detail: Option<MemoryDetail>,
     #[serde(rename = "diskUsage")]
     pub disk_usage: f64,
+    #[serde(default)]
+    pub mounts: Vec<MountData>,
     #[serde(rename = "ioRead")]
     pub io_read: f64,
     #[serde(rename = "ioWrite")]
     pub io_write: f64,
+    #[serde(rename = "ioDevices", default)]
+    pub io_devices: Vec<DiskIoData>,
     #[serde(rename = "networkIn")]
     pub network_in: f64,
     #[serde(rename = "networkOut")]
     pub network_out: f64,
+    #[serde(rename = "networkInterfaces", default)]
+    pub network_interfaces: Vec<NetInterfaceData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<CgroupMetrics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensors: Option<SensorData>,
 }
 
-pub fn collect_metrics() -> Result<String, Box<dyn std::error::Error>> {
-    // 生成服务器ID
-    let server_id = util::generate_server_id();
-    
-    // 获取当前时间戳（毫秒）
-    let timestamp = Utc::now().timestamp_millis() as u64;
-    
-    // 初始化系统信息
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    
-    // 等待一小段时间后再次刷新，以获取准确的CPU使用率
-    std::thread::sleep(std::time::Duration::from_millis(200));
-    sys.refresh_cpu_all();
-    
-    // 计算CPU使用率（所有核心的平均值）
-    let cpu_usage = sys.cpus().iter()
-        .map(|cpu| cpu.cpu_usage() as f64)
-        .sum::<f64>() / sys.cpus().len() as f64;
-    
-    // 计算内存使用率
-    let total_memory = sys.total_memory() as f64;
-    let used_memory = sys.used_memory() as f64;
-    let memory_usage = if total_memory > 0.0 {
-        (used_memory / total_memory) * 100.0
-    } else {
-        0.0
-    };
-    
-    // 计算磁盘使用率
-    let disks = Disks::new_with_refreshed_list();
-    let (total_disk, used_disk) = disks.iter().fold((0u64, 0u64), |(total, used), disk| {
-        let disk_total = disk.total_space();
-        let disk_available = disk.available_space();
-        let disk_used = disk_total.saturating_sub(disk_available);
-        (total + disk_total, used + disk_used)
-    });
-    
-    let disk_usage = if total_disk > 0 {
-        (used_disk as f64 / total_disk as f64) * 100.0
-    } else {
-        0.0
-    };
-    
-    // 获取网络统计信息
-    let networks = Networks::new_with_refreshed_list();
-    std::thread::sleep(std::time::Duration::from_millis(100));
-    let mut networks = networks;
-    networks.refresh();
-    
-    let (network_in, network_out) = networks.iter().fold((0u64, 0u64), |(rx, tx), (_, data)| {
-        (rx + data.received(), tx + data.transmitted())
-    });
-    
-    // 转换为 KB/s (除以1024)
-    let network_in_kb = network_in as f64 / 1024.0;
-    let network_out_kb = network_out as f64 / 1024.0;
-    
-    // IO读写数据（Linux特定）
-    let (io_read, io_write) = get_io_stats();
-    
-    let metrics = MetricsData {
-        server_id,
-        timestamp,
-        cpu_usage: (cpu_usage * 10.0).round() / 10.0, // 保留一位小数
-        memory_usage: (memory_usage * 10.0).round() / 10.0,
-        disk_usage: (disk_usage * 10.0).round() / 10.0,
-        io_read,
-        io_write,
-        network_in: (network_in_kb * 10.0).round() / 10.0,
-        network_out: (network_out_kb * 10.0).round() / 10.0,
-    };
-    
-    // 将单个指标数据包装在数组中
-    let metrics_array = vec![metrics];
-    
-    // 序列化为JSON字符串（格式化输出）
-    let json_string = serde_json::to_string_pretty(&metrics_array)?;
-    
-    Ok(json_string)
+/// 指标采集配置
+#[derive(Debug, Clone, Default, Args)]
+pub struct MetricsConfig {
+    /// 采集自身所在 cgroup v2 的指标，cpuUsage/memoryUsage 改为相对 cgroup 限制计算
+    #[arg(long)]
+    pub cgroup: bool,
+    #[command(flatten)]
+    pub mounts: MountFilter,
+    #[command(flatten)]
+    pub interfaces: InterfaceFilter,
 }
 
-// 获取IO统计信息
-fn get_io_stats() -> (f64, f64) {
-    #[cfg(target_os = "linux")]
-    {
-        use std::fs;
-        
-        if let Ok(content) = fs::read_to_string("/proc/diskstats") {
-            let (total_read, total_write) = content.lines()
-                .filter_map(|line| {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 14 {
-                        // 只统计主要设备（不包括分区）
-                        let device_name = parts[2];
-                        if !device_name.chars().last().unwrap_or('0').is_ascii_digit() {
-                            // sectors read (字段6) 和 sectors written (字段10)
-                            // 每个扇区通常是512字节
-                            let read_sectors = parts[5].parse::<u64>().ok()?;
-                            let write_sectors = parts[9].parse::<u64>().ok()?;
-                            return Some((read_sectors, write_sectors));
-                        }
-                    }
-                    None
-                })
-                .fold((0u64, 0u64), |(r, w), (read, write)| (r + read, w + write));
-            
-            // 转换为MB (扇区 * 512 / 1024 / 1024)
-            let read_mb = (total_read as f64 * 512.0) / (1024.0 * 1024.0);
-            let write_mb = (total_write as f64 * 512.0) / (1024.0 * 1024.0);
-            
-            return ((read_mb * 10.0).round() / 10.0, (write_mb * 10.0).round() / 10.0);
+/// 指标采集器，在多次采集之间保留需要计算速率的计数
+///
+/// 只有首次采集需要等待一小段时间获取基准数据，之后每次采集都与上一次采集比较
+#[derive(Debug, Default)]
+pub struct MetricsCollector {
+    config: MetricsConfig,
+    sys: System,
+    primed: bool,
+    cpu_stat: Option<CpuStatSnapshot>,
+    disk_io: DiskIoSampler,
+    network: NetworkSampler,
+    cgroup: CgroupSampler,
+}
+
+impl MetricsCollector {
+    pub fn new(config: MetricsConfig) -> Self {
+        MetricsCollector {
+            config,
+            ..Default::default()
         }
-        (0.0, 0.0)
     }
-    
-    #[cfg(not(target_os = "linux"))]
-    {
-        // macOS 和其他系统暂时返回0
-        (0.0, 0.0)
+
+    pub fn collect(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        // 生成服务器ID
+        let server_id = util::generate_server_id();
+        
+        // 获取当前时间戳（毫秒）
+        let timestamp = Utc::now().timestamp_millis() as u64;
+        
+        // 读取目录快照时不使用本机数据
+        let live = source::current().is_live();
+        
+        // 首次采集先记录基准数据，等待一小段时间后再采样，以获取准确的CPU使用率
+        // （快照数据不会变化，CPU、磁盘与网络改为输出自启动以来的平均值，无需基准）
+        if !self.primed {
+            if live {
+                self.sys.refresh_cpu_usage();
+                self.cpu_stat = cpu::read_cpu_stat();
+                self.disk_io.prime();
+                self.network.prime();
+            }
+            if self.config.cgroup {
+                self.cgroup.prime();
+            }
+            if live {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            self.primed = true;
+        }
+        
+        // 只刷新需要的 CPU 与内存信息
+        if live {
+            self.sys.refresh_cpu_usage();
+            self.sys.refresh_memory();
+        }
+        
+        // 从 /proc/stat 计算各核心使用率及 user/system/iowait 等分项
+        let cpu_stat = cpu::read_cpu_stat();
+        let (cpu_breakdown, cpu_cores) = match (&self.cpu_stat, &cpu_stat) {
+            (_, Some(after)) if !live => {
+                let (total, cores) = cpu::usage_since_boot(after);
+                (Some(total), cores)
+            }
+            (Some(before), Some(after)) => {
+                let (total, cores) = cpu::usage_between(before, after);
+                (Some(total), cores)
+            }
+            _ => (None, Vec::new()),
+        };
+        self.cpu_stat = cpu_stat;
+        
+        // 计算CPU使用率（所有核心的平均值）
+        let mut cpu_usage = if live {
+            self.sys.cpus().iter()
+                .map(|cpu| cpu.cpu_usage() as f64)
+                .sum::<f64>() / self.sys.cpus().len() as f64
+        } else {
+            cpu_breakdown.as_ref().map(|t| t.usage).unwrap_or(0.0)
+        };
+        
+        // 平均负载及 PSI（内核不支持时省略）
+        let load_average = load::read_load_average();
+        let pressure = load::read_pressure();
+        
+        // 内存明细（swap、缓存、大页等）
+        let memory_detail = meminfo::read_memory_detail();
+        
+        // 计算内存使用率
+        let (total_memory, used_memory) = match &memory_detail {
+            Some(detail) if !live => (detail.total as f64, detail.total.saturating_sub(detail.available) as f64),
+            _ => (self.sys.total_memory() as f64, self.sys.used_memory() as f64),
+        };
+        let mut memory_usage = if total_memory > 0.0 {
+            (used_memory / total_memory) * 100.0
+        } else {
+            0.0
+        };
+        
+        // cgroup 模式下按 cgroup 的配额与内存上限计算使用率
+        let cgroup = if self.config.cgroup { self.cgroup.sample() } else { None };
+        if let Some(cg) = &cgroup {
+            cpu_usage = cg.cpu.usage;
+            memory_usage = match cg.memory.usage {
+                Some(usage) => usage,
+                None if total_memory > 0.0 => cg.memory.current as f64 / total_memory * 100.0,
+                None => 0.0,
+            };
+        }
+        
+        // 计算磁盘使用率（按挂载点，过滤伪文件系统）
+        let mounts = mounts::collect_mounts(&self.config.mounts);
+        let disk_usage = mounts::aggregate_usage(&mounts);
+        
+        let uptime = source::current().uptime().unwrap_or(0.0);
+        
+        // 获取网络统计信息（KB/s），只汇总选中的接口
+        let network_interfaces = if live {
+            self.network.sample(&self.config.interfaces)
+        } else {
+            self.network.sample_since_boot(&self.config.interfaces, uptime)
+        };
+        let (network_in_kb, network_out_kb) = network_interfaces.iter()
+            .filter(|iface| iface.in_total)
+            .fold((0.0, 0.0), |(rx, tx), iface| (rx + iface.rx_rate, tx + iface.tx_rate));
+        
+        // IO读写速率（MB/s）
+        let io_devices = if live { self.disk_io.sample() } else { self.disk_io.sample_since_boot(uptime) };
+        let (io_read, io_write) = io_devices.iter()
+            .fold((0.0, 0.0), |(r, w), dev| (r + dev.read_rate, w + dev.write_rate));
+        
+        // 温度、风扇与温控区（没有传感器时省略）
+        let sensors = Some(sensors::collect_sensors(source::current().sys_root()))
+            .filter(|data| !data.is_empty());
+        
+        let metrics = MetricsData {
+            server_id,
+            timestamp,
+            cpu_usage: (cpu_usage * 10.0).round() / 10.0, // 保留一位小数
+            cpu_breakdown,
+            cpu_cores,
+            load_average,
+            pressure,
+            memory_usage: (memory_usage * 10.0).round() / 10.0,
+            memory_detail,
+            disk_usage,
+            mounts,
+            io_read: (io_read * 10.0).round() / 10.0,
+            io_write: (io_write * 10.0).round() / 10.0,
+            io_devices,
+            network_in: (network_in_kb * 10.0).round() / 10.0,
+            network_out: (network_out_kb * 10.0).round() / 10.0,
+            network_interfaces,
+            cgroup,
+            sensors,
+        };
+        
+        // 将单个指标数据包装在数组中
+        let metrics_array = vec![metrics];
+        
+        // 序列化为JSON字符串（格式化输出）
+        let json_string = serde_json::to_string_pretty(&metrics_array)?;
+        
+        Ok(json_string)
+    }
+}
+
+/// 单次采集指标（用于 metrics 子命令）
+pub fn collect_metrics(config: MetricsConfig) -> Result<String, Box<dyn std::error::Error>> {
+    MetricsCollector::new(config).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_reports_averages_since_boot() {
+        let fixture = source::fixture::Fixture::new();
+        fixture.write(&[
+            ("proc/stat", "cpu  600 0 200 1000 200 0 0 0 0 0\ncpu0 400 0 100 400 100 0 0 0 0 0\ncpu1 200 0 100 600 100 0 0 0 0 0\nbtime 1700000000\n"),
+            ("proc/uptime", "100.00 180.00\n"),
+            ("proc/meminfo", "MemTotal:        1000000 kB\nMemFree:          100000 kB\nMemAvailable:     250000 kB\n"),
+            ("proc/diskstats", "   8       0 sda 1000 0 204800 500 2000 0 409600 1000 0 3000 1500\n   8       1 sda1 900 0 200000 400 1900 0 400000 900 0 2900 1300\n"),
+            ("proc/net/dev", "Inter-|   Receive                                                |  Transmit\n face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n  eth0: 1024000 100 0 0 0 0 0 0 2048000 200 0 0 0 0 0 0\n    lo: 512000 10 0 0 0 0 0 0 512000 10 0 0 0 0 0 0\n"),
+            ("proc/sys/kernel/hostname", "fixture-host\n"),
+            ("identity.json", r#"{"machineId": "0123456789abcdef", "serverIp": "192.0.2.10"}"#),
+            ("proc/self/mounts", "/dev/sda1 / ext4 rw,relatime 0 0\nproc /proc proc rw 0 0\n"),
+            ("statvfs.json", r#"{"/": {"blockSize": 4096, "blocks": 1000, "blocksFree": 400, "blocksAvailable": 350,
+                "files": 100, "filesFree": 75, "readOnly": false}}"#),
+        ]);
+        std::fs::create_dir_all(fixture.root().join("sys/block/sda/device")).unwrap();
+
+        // 快照数据不变，连续两次采集结果应相同
+        let mut collector = MetricsCollector::new(MetricsConfig::default());
+        let first: Vec<MetricsData> = serde_json::from_str(&collector.collect().unwrap()).unwrap();
+        let second: Vec<MetricsData> = serde_json::from_str(&collector.collect().unwrap()).unwrap();
+
+        for metrics in [&first[0], &second[0]] {
+            assert_eq!(metrics.server_id, "fixture-host-01234567");
+            assert_eq!(metrics.cpu_usage, 40.0);
+            let breakdown = metrics.cpu_breakdown.as_ref().unwrap();
+            assert_eq!(breakdown.user, 30.0);
+            assert_eq!(breakdown.system, 10.0);
+            assert_eq!(breakdown.iowait, 10.0);
+            assert_eq!(metrics.cpu_cores.len(), 2);
+            assert_eq!(metrics.cpu_cores[0].times.usage, 50.0);
+            assert_eq!(metrics.cpu_cores[1].times.usage, 30.0);
+            assert_eq!(metrics.memory_usage, 75.0);
+
+            // 只统计整盘：100 MiB 读、200 MiB 写，运行 100 秒
+            assert_eq!(metrics.io_devices.len(), 1);
+            assert_eq!(metrics.io_read, 1.0);
+            assert_eq!(metrics.io_write, 2.0);
+
+            // lo 默认不计入总流量
+            assert_eq!(metrics.network_interfaces.len(), 2);
+            assert_eq!(metrics.network_in, 10.0);
+            assert_eq!(metrics.network_out, 20.0);
+
+            // 挂载点使用录制的 statvfs 结果：已用 600 块，用户可用 350 块
+            assert_eq!(metrics.mounts.len(), 1);
+            assert_eq!(metrics.mounts[0].mount_point, "/");
+            assert_eq!(metrics.mounts[0].used, 600 * 4096);
+            assert_eq!(metrics.mounts[0].usage, 63.2);
+            assert_eq!(metrics.mounts[0].inode_usage, Some(25.0));
+            assert_eq!(metrics.disk_usage, 63.2);
+        }
     }
 }
@@ -0,0 +1,253 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use crate::{source, util};
+
+/// 默认忽略的伪文件系统类型
+const PSEUDO_FS_TYPES: &[&str] = &[
+    "proc", "sysfs", "devtmpfs", "devpts", "tmpfs", "ramfs", "squashfs", "overlay",
+    "cgroup", "cgroup2", "pstore", "bpf", "tracefs", "debugfs", "securityfs", "configfs",
+    "fusectl", "mqueue", "hugetlbfs", "autofs", "binfmt_misc", "rpc_pipefs", "nsfs",
+    "efivarfs", "selinuxfs", "iso9660", "fuse.gvfsd-fuse", "fuse.portal",
+];
+
+/// 挂载点过滤条件
+#[derive(Debug, Clone, Default, Args)]
+pub struct MountFilter {
+    /// 只统计这些挂载点（可多次指定）
+    #[arg(long = "mount-include", value_name = "PATH")]
+    pub include_mounts: Vec<String>,
+    /// 不统计这些挂载点（可多次指定）
+    #[arg(long = "mount-exclude", value_name = "PATH")]
+    pub exclude_mounts: Vec<String>,
+    /// 额外统计的文件系统类型，例如 tmpfs（可多次指定）
+    #[arg(long = "fs-include", value_name = "FSTYPE")]
+    pub include_fs_types: Vec<String>,
+    /// 额外排除的文件系统类型（可多次指定）
+    #[arg(long = "fs-exclude", value_name = "FSTYPE")]
+    pub exclude_fs_types: Vec<String>,
+}
+
+impl MountFilter {
+    fn accepts(&self, mount_point: &str, fs_type: &str) -> bool {
+        if self.exclude_mounts.iter().any(|m| m == mount_point)
+            || self.exclude_fs_types.iter().any(|t| t == fs_type)
+        {
+            return false;
+        }
+        if !self.include_mounts.is_empty() && !self.include_mounts.iter().any(|m| m == mount_point) {
+            return false;
+        }
+        !PSEUDO_FS_TYPES.contains(&fs_type) || self.include_fs_types.iter().any(|t| t == fs_type)
+    }
+}
+
+/// 单个挂载点的空间使用情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountData {
+    pub device: String,
+    #[serde(rename = "mountPoint")]
+    pub mount_point: String,
+    #[serde(rename = "fsType")]
+    pub fs_type: String,
+    /// 总空间（字节）
+    pub total: u64,
+    /// 已用空间（字节）
+    pub used: u64,
+    /// 普通用户可用空间（字节）
+    pub available: u64,
+    /// 空间使用率 (%)，与 df 的计算方式一致
+    pub usage: f64,
+    /// inode 使用率 (%)，文件系统不提供 inode 信息时为空
+    #[serde(rename = "inodeUsage", default, skip_serializing_if = "Option::is_none")]
+    pub inode_usage: Option<f64>,
+    #[serde(rename = "readOnly")]
+    pub read_only: bool,
+}
+
+/// 挂载点的 statvfs 结果，capture 录制到快照中供回放时使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsStats {
+    /// 块大小（f_frsize，字节）
+    #[serde(rename = "blockSize")]
+    pub block_size: u64,
+    pub blocks: u64,
+    #[serde(rename = "blocksFree")]
+    pub blocks_free: u64,
+    /// 普通用户可用的块数
+    #[serde(rename = "blocksAvailable")]
+    pub blocks_available: u64,
+    pub files: u64,
+    #[serde(rename = "filesFree")]
+    pub files_free: u64,
+    #[serde(rename = "readOnly")]
+    pub read_only: bool,
+}
+
+/// 采集通过过滤条件的挂载点
+///
+/// 目录快照中无法调用 statvfs，使用 capture 录制的结果
+pub fn collect_mounts(filter: &MountFilter) -> Vec<MountData> {
+    #[cfg(target_os = "linux")]
+    {
+        let source = source::current();
+        let recorded = (!source.is_live()).then(crate::capture::read_fs_stats);
+
+        mount_entries()
+            .into_iter()
+            .filter(|mount| filter.accepts(&mount.mount_point, &mount.fs_type))
+            .filter_map(|mount| {
+                let stats = match &recorded {
+                    Some(recorded) => recorded.get(&mount.mount_point).cloned(),
+                    None => statvfs(&mount.mount_point),
+                }?;
+                mount_data(mount, &stats)
+            })
+            .collect()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        // 其他系统没有 /proc/self/mounts，使用 sysinfo 提供的磁盘列表，不含 inode 信息
+        use sysinfo::Disks;
+
+        Disks::new_with_refreshed_list().iter()
+            .filter_map(|disk| {
+                let mount_point = disk.mount_point().to_string_lossy().to_string();
+                let fs_type = disk.file_system().to_string_lossy().to_string();
+                if !filter.accepts(&mount_point, &fs_type) {
+                    return None;
+                }
+                let total = disk.total_space();
+                let available = disk.available_space();
+                let used = total.saturating_sub(available);
+                Some(MountData {
+                    device: disk.name().to_string_lossy().to_string(),
+                    mount_point,
+                    fs_type,
+                    total,
+                    used,
+                    available,
+                    usage: percent(used, used + available),
+                    inode_usage: None,
+                    read_only: disk.is_read_only(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 汇总磁盘使用率，同一设备的多个挂载点（bind mount 等）只计算一次
+pub fn aggregate_usage(mounts: &[MountData]) -> f64 {
+    let mut seen = std::collections::HashSet::new();
+    let (used, available) = mounts.iter()
+        .filter(|m| seen.insert(m.device.as_str()))
+        .fold((0u64, 0u64), |(used, avail), m| (used + m.used, avail + m.available));
+    percent(used, used + available)
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole > 0 {
+        util::round1(part as f64 / whole as f64 * 100.0)
+    } else {
+        0.0
+    }
+}
+
+/// 录制当前数据源中所有挂载点的 statvfs 结果，键为挂载点
+#[cfg(target_os = "linux")]
+pub fn record_fs_stats() -> std::collections::BTreeMap<String, FsStats> {
+    mount_entries()
+        .into_iter()
+        .filter_map(|mount| Some((mount.mount_point.clone(), statvfs(&mount.mount_point)?)))
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn record_fs_stats() -> std::collections::BTreeMap<String, FsStats> {
+    std::collections::BTreeMap::new()
+}
+
+/// /proc/self/mounts 中的一行
+#[cfg(target_os = "linux")]
+struct MountEntry {
+    device: String,
+    mount_point: String,
+    fs_type: String,
+    read_only: bool,
+}
+
+#[cfg(target_os = "linux")]
+fn mount_entries() -> Vec<MountEntry> {
+    let Ok(content) = source::current().read_proc("self/mounts") else {
+        return Vec::new();
+    };
+    content.lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 4 {
+                return None;
+            }
+            Some(MountEntry {
+                device: parts[0].to_string(),
+                mount_point: unescape_mount_path(parts[1]),
+                fs_type: parts[2].to_string(),
+                read_only: parts[3].split(',').any(|opt| opt == "ro"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn statvfs(mount_point: &str) -> Option<FsStats> {
+    use std::ffi::CString;
+
+    let path = CString::new(mount_point).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    Some(FsStats {
+        block_size: stat.f_frsize as u64,
+        blocks: stat.f_blocks as u64,
+        blocks_free: stat.f_bfree as u64,
+        blocks_available: stat.f_bavail as u64,
+        files: stat.f_files as u64,
+        files_free: stat.f_ffree as u64,
+        read_only: stat.f_flag & libc::ST_RDONLY != 0,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn mount_data(mount: MountEntry, stats: &FsStats) -> Option<MountData> {
+    let total = stats.blocks * stats.block_size;
+    let free = stats.blocks_free * stats.block_size;
+    let available = stats.blocks_available * stats.block_size;
+    let used = total.saturating_sub(free);
+    if total == 0 {
+        return None;
+    }
+
+    let inode_usage = (stats.files > 0).then(|| percent(stats.files.saturating_sub(stats.files_free), stats.files));
+
+    Some(MountData {
+        device: mount.device,
+        mount_point: mount.mount_point,
+        fs_type: mount.fs_type,
+        total,
+        used,
+        available,
+        usage: percent(used, used + available),
+        inode_usage,
+        read_only: mount.read_only || stats.read_only,
+    })
+}
+
+// /proc/self/mounts 中空格、制表符等以八进制转义
+#[cfg(target_os = "linux")]
+fn unescape_mount_path(path: &str) -> String {
+    path.replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
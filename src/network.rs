@@ -0,0 +1,257 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use crate::{source, util};
+
+/// 默认不计入总流量的接口（回环、容器及虚拟网桥）
+const DEFAULT_EXCLUDED_INTERFACES: &[&str] = &["lo", "docker*", "veth*", "br-*", "virbr*"];
+
+/// 网络接口选择条件
+#[derive(Debug, Clone, Default, Args)]
+pub struct InterfaceFilter {
+    /// 只把这些接口计入总流量，支持末尾 * 通配（可多次指定）
+    #[arg(long = "net-include", value_name = "IFACE")]
+    pub include: Vec<String>,
+    /// 不把这些接口计入总流量，支持末尾 * 通配（可多次指定）
+    #[arg(long = "net-exclude", value_name = "IFACE")]
+    pub exclude: Vec<String>,
+}
+
+impl InterfaceFilter {
+    fn counts_toward_total(&self, name: &str) -> bool {
+        if self.exclude.iter().any(|p| matches_pattern(p, name)) {
+            return false;
+        }
+        if !self.include.is_empty() {
+            return self.include.iter().any(|p| matches_pattern(p, name));
+        }
+        !DEFAULT_EXCLUDED_INTERFACES.iter().any(|p| matches_pattern(p, name))
+    }
+}
+
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// 单个网络接口在采样间隔内的流量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetInterfaceData {
+    pub name: String,
+    /// 接收速率 (KB/s)
+    #[serde(rename = "rxRate")]
+    pub rx_rate: f64,
+    /// 发送速率 (KB/s)
+    #[serde(rename = "txRate")]
+    pub tx_rate: f64,
+    #[serde(rename = "rxPackets")]
+    pub rx_packets: f64,
+    #[serde(rename = "txPackets")]
+    pub tx_packets: f64,
+    /// 采样间隔内新增的接收错误数
+    #[serde(rename = "rxErrors")]
+    pub rx_errors: u64,
+    #[serde(rename = "txErrors")]
+    pub tx_errors: u64,
+    /// 采样间隔内新增的丢包数
+    #[serde(rename = "rxDropped")]
+    pub rx_dropped: u64,
+    #[serde(rename = "txDropped")]
+    pub tx_dropped: u64,
+    /// 是否计入 networkIn/networkOut
+    #[serde(rename = "inTotal")]
+    pub in_total: bool,
+}
+
+/// 单个接口的累计计数
+#[derive(Debug, Clone, Copy, Default)]
+struct NetCounters {
+    rx_bytes: u64,
+    rx_packets: u64,
+    rx_errors: u64,
+    rx_dropped: u64,
+    tx_bytes: u64,
+    tx_packets: u64,
+    tx_errors: u64,
+    tx_dropped: u64,
+}
+
+/// 网络采样器，在多次采集之间保留上一次的计数
+#[derive(Debug, Default)]
+pub struct NetworkSampler {
+    prev: Option<(Instant, HashMap<String, NetCounters>)>,
+}
+
+impl NetworkSampler {
+    /// 尚无基准数据时先记录一次计数
+    pub fn prime(&mut self) {
+        if self.prev.is_none() {
+            self.prev = Some((Instant::now(), read_net_counters()));
+        }
+    }
+
+    /// 采样并返回与上次采样之间每个接口的速率
+    pub fn sample(&mut self, filter: &InterfaceFilter) -> Vec<NetInterfaceData> {
+        let now = Instant::now();
+        let counters = read_net_counters();
+        let mut result = Vec::new();
+
+        if let Some((prev_time, prev_counters)) = &self.prev {
+            let elapsed = now.duration_since(*prev_time).as_secs_f64();
+            if elapsed > 0.0 {
+                for (name, current) in &counters {
+                    let Some(prev) = prev_counters.get(name) else { continue };
+                    let Some(data) = rates_between(name, prev, current, elapsed) else { continue };
+                    result.push(NetInterfaceData {
+                        in_total: filter.counts_toward_total(name),
+                        ..data
+                    });
+                }
+            }
+        }
+
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        self.prev = Some((now, counters));
+        result
+    }
+
+    /// 返回自系统启动以来每个接口的平均速率，用于只有一次采样的目录快照
+    ///
+    /// 启动后才创建的接口同样按系统运行时间平均，结果偏低
+    pub fn sample_since_boot(&self, filter: &InterfaceFilter, uptime: f64) -> Vec<NetInterfaceData> {
+        if uptime <= 0.0 {
+            return Vec::new();
+        }
+        let mut result: Vec<NetInterfaceData> = read_net_counters().iter()
+            .filter_map(|(name, current)| {
+                let data = rates_between(name, &NetCounters::default(), current, uptime)?;
+                Some(NetInterfaceData {
+                    in_total: filter.counts_toward_total(name),
+                    ..data
+                })
+            })
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        result
+    }
+}
+
+/// 计算计数增量，计数回退说明接口被重建或计数被清零，返回 None 跳过本次采样
+///
+/// /proc/net/dev 的计数为 64 位，不按 32 位回绕处理，否则接口重建会产生约 4 GiB 的虚假增量
+fn counter_delta(prev: u64, now: u64) -> Option<u64> {
+    now.checked_sub(prev)
+}
+
+fn rates_between(name: &str, prev: &NetCounters, now: &NetCounters, elapsed: f64) -> Option<NetInterfaceData> {
+    let rx_bytes = counter_delta(prev.rx_bytes, now.rx_bytes)?;
+    let tx_bytes = counter_delta(prev.tx_bytes, now.tx_bytes)?;
+    let rx_packets = counter_delta(prev.rx_packets, now.rx_packets)?;
+    let tx_packets = counter_delta(prev.tx_packets, now.tx_packets)?;
+
+    Some(NetInterfaceData {
+        name: name.to_string(),
+        rx_rate: util::round1(rx_bytes as f64 / 1024.0 / elapsed),
+        tx_rate: util::round1(tx_bytes as f64 / 1024.0 / elapsed),
+        rx_packets: util::round1(rx_packets as f64 / elapsed),
+        tx_packets: util::round1(tx_packets as f64 / elapsed),
+        rx_errors: counter_delta(prev.rx_errors, now.rx_errors).unwrap_or(0),
+        tx_errors: counter_delta(prev.tx_errors, now.tx_errors).unwrap_or(0),
+        rx_dropped: counter_delta(prev.rx_dropped, now.rx_dropped).unwrap_or(0),
+        tx_dropped: counter_delta(prev.tx_dropped, now.tx_dropped).unwrap_or(0),
+        in_total: false,
+    })
+}
+
+// 读取所有接口的累计计数
+fn read_net_counters() -> HashMap<String, NetCounters> {
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(content) = source::current().read_proc("net/dev") else {
+            return HashMap::new();
+        };
+
+        // 前两行为表头，格式: iface: rx_bytes rx_packets rx_errs rx_drop ... tx_bytes tx_packets tx_errs tx_drop ...
+        content.lines()
+            .skip(2)
+            .filter_map(|line| {
+                let (name, values) = line.split_once(':')?;
+                let values: Vec<u64> = values.split_whitespace()
+                    .map(|v| v.parse::<u64>().ok())
+                    .collect::<Option<_>>()?;
+                if values.len() < 12 {
+                    return None;
+                }
+                Some((name.trim().to_string(), NetCounters {
+                    rx_bytes: values[0],
+                    rx_packets: values[1],
+                    rx_errors: values[2],
+                    rx_dropped: values[3],
+                    tx_bytes: values[8],
+                    tx_packets: values[9],
+                    tx_errors: values[10],
+                    tx_dropped: values[11],
+                }))
+            })
+            .collect()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        // 其他系统使用 sysinfo 的累计计数，没有丢包信息
+        use sysinfo::Networks;
+
+        Networks::new_with_refreshed_list().iter()
+            .map(|(name, data)| (name.clone(), NetCounters {
+                rx_bytes: data.total_received(),
+                rx_packets: data.total_packets_received(),
+                rx_errors: data.total_errors_on_received(),
+                tx_bytes: data.total_transmitted(),
+                tx_packets: data.total_packets_transmitted(),
+                tx_errors: data.total_errors_on_transmitted(),
+                ..Default::default()
+            }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counters(rx_bytes: u64, tx_bytes: u64) -> NetCounters {
+        NetCounters {
+            rx_bytes,
+            tx_bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counter_delta_increases() {
+        assert_eq!(counter_delta(100, 250), Some(150));
+        assert_eq!(counter_delta(100, 100), Some(0));
+    }
+
+    #[test]
+    fn counter_delta_reset_is_skipped() {
+        // 接口重建后计数从 0 重新开始，旧值在 32 位范围内也不能当作回绕
+        assert_eq!(counter_delta(4_000_000, 1_000), None);
+        assert_eq!(counter_delta(u64::MAX - 10, 5), None);
+    }
+
+    #[test]
+    fn reset_interface_has_no_rate() {
+        let prev = counters(3_000_000, 2_000_000);
+        let now = counters(500, 200);
+        assert!(rates_between("veth0", &prev, &now, 1.0).is_none());
+
+        let now = counters(3_102_400, 2_000_000);
+        let rates = rates_between("veth0", &prev, &now, 1.0).unwrap();
+        assert_eq!(rates.rx_rate, 100.0);
+        assert_eq!(rates.tx_rate, 0.0);
+    }
+}
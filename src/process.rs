@@ -1,23 +1,27 @@
+use clap::{Args, ValueEnum};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sysinfo::{System};
 use chrono::Utc;
-use crate::util;
-use local_ip_address::local_ip;
+use crate::lifecycle::{LifecycleTracker, ObservedProcess, ProcessEvent};
+use crate::ranking::{OutputFormat, ProcessRanking, RankKey, RankedProcess, RankingConfig};
+use crate::resources::{self, ProcessResources};
+use crate::thread_limits::{self, ThreadLimitConfig, ThreadLimitReport};
+use crate::trend::{TrendConfig, TrendHistory};
+use crate::{cgroup, source, util};
 use std::process::Command;
+use std::collections::{BTreeSet, HashMap, HashSet};
+#[cfg(target_os = "linux")]
+use std::time::Instant;
+
+/// 按当前数据源打开 /proc/<pid>
+#[cfg(target_os = "linux")]
+fn open_process(pid: u32) -> procfs::ProcResult<procfs::process::Process> {
+    procfs::process::Process::new_with_root(source::current().proc_path(pid.to_string()))
+}
 
 // 获取进程线程数的跨平台函数
+#[cfg(not(target_os = "linux"))]
 fn get_thread_count(pid: u32) -> u32 {
-    #[cfg(target_os = "linux")]
-    {
-        use procfs::process::Process;
-        if let Ok(proc) = Process::new(pid as i32) {
-            if let Ok(stat) = proc.stat() {
-                return stat.num_threads as u32;
-            }
-        }
-        1
-    }
-    
     #[cfg(target_os = "macos")]
     {
         use std::mem;
@@ -41,103 +45,800 @@ fn get_thread_count(pid: u32) -> u32 {
         1
     }
     
-    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    #[cfg(not(target_os = "macos"))]
     {
         let _ = pid;
         1
     }
 }
 
-// 获取进程的所有线程详细信息（最多10个）
-fn get_thread_details(pid: u32, process_user: &str) -> Vec<ThreadData> {
+/// 未指定其他选择条件时默认的最小线程数
+const DEFAULT_MIN_THREADS: u32 = 20;
+
+/// top-N 排序依据
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ProcessSortKey {
+    #[default]
+    Cpu,
+    Memory,
+}
+
+/// 进程选择条件，多个条件同时满足才会输出
+#[derive(Debug, Clone, Default, Args)]
+pub struct ProcessFilter {
+    /// 输出所有进程，不做任何过滤
+    #[arg(long, conflicts_with_all = ["min_threads", "names", "users", "pids", "top"])]
+    pub all: bool,
+    /// 最小线程数，默认 20，指定了名称、用户、PID 或 top 条件时默认不限制
+    #[arg(long, value_name = "N")]
+    pub min_threads: Option<u32>,
+    /// 按进程名匹配的正则表达式（可多次指定，满足任意一个即可）
+    #[arg(long = "name", value_name = "REGEX")]
+    pub names: Vec<Regex>,
+    /// 只输出这些用户的进程（可多次指定）
+    #[arg(long = "user", value_name = "USER")]
+    pub users: Vec<String>,
+    /// 只输出这些 PID，逗号分隔或多次指定
+    #[arg(long = "pid", value_name = "PID", value_delimiter = ',')]
+    pub pids: Vec<u32>,
+    /// 只输出排序后的前 N 个进程
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+    /// top 的排序依据
+    #[arg(long, value_enum, default_value_t = ProcessSortKey::Cpu, requires = "top")]
+    pub top_by: ProcessSortKey,
+}
+
+impl ProcessFilter {
+    /// 是否指定了名称、用户、PID 或 top 条件
+    fn has_selector(&self) -> bool {
+        !self.names.is_empty()
+            || !self.users.is_empty()
+            || !self.pids.is_empty()
+            || self.top.is_some()
+    }
+
+    fn min_threads(&self) -> u32 {
+        if self.all {
+            return 0;
+        }
+        self.min_threads.unwrap_or(if self.has_selector() { 0 } else { DEFAULT_MIN_THREADS })
+    }
+
+    fn accepts(&self, process: &ProcessEntry) -> bool {
+        if self.all {
+            return true;
+        }
+        process.thread_count >= self.min_threads() && self.matches_identity(process)
+    }
+
+    /// 只按 PID、用户与名称判断，不考虑线程数与 top 条件
+    fn matches_identity(&self, process: &ProcessEntry) -> bool {
+        (self.pids.is_empty() || self.pids.contains(&process.pid))
+            && (self.users.is_empty() || self.users.contains(&process.user_name))
+            && (self.names.is_empty() || self.names.iter().any(|re| re.is_match(&process.name)))
+    }
+
+    /// 排名时使用：按 PID、用户与名称匹配，只有显式指定最小线程数时才按线程数过滤
+    fn accepts_for_ranking(&self, process: &ProcessEntry) -> bool {
+        process.thread_count >= self.min_threads.unwrap_or(0) && self.matches_identity(process)
+    }
+
+    /// 按条件过滤，指定 top 时再按 CPU 或内存降序截取前 N 个，返回选中进程的下标
+    fn select_indices(&self, processes: &[ProcessEntry]) -> Vec<usize> {
+        let mut selected: Vec<usize> = (0..processes.len())
+            .filter(|&index| self.accepts(&processes[index]))
+            .collect();
+        if let Some(top) = self.top {
+            match self.top_by {
+                ProcessSortKey::Cpu => selected.sort_by(|&a, &b| processes[b].cpu_usage.total_cmp(&processes[a].cpu_usage)),
+                ProcessSortKey::Memory => selected.sort_by_key(|&index| std::cmp::Reverse(processes[index].memory)),
+            }
+            selected.truncate(top);
+        }
+        selected
+    }
+
+    fn select(&self, processes: Vec<ProcessEntry>) -> Vec<ProcessEntry> {
+        let indices = self.select_indices(&processes);
+        let mut slots: Vec<Option<ProcessEntry>> = processes.into_iter().map(Some).collect();
+        indices.into_iter().filter_map(|index| slots[index].take()).collect()
+    }
+}
+
+/// IO 排行默认输出的进程数
+const TOP_IO_PROCESSES: usize = 10;
+
+/// 每个进程默认输出的线程数
+const DEFAULT_THREAD_TOP: usize = 10;
+
+/// 线程排序依据，均为降序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ThreadSortKey {
+    /// CPU 使用率，还没有上一次采样的线程排在后面，按累计 CPU 时间排序
+    #[default]
+    Cpu,
+    /// 常驻内存，同一进程的线程共享地址空间，通常相同
+    Rss,
+    /// 按 D、R、Z、T、S、I 的顺序，越可能有问题的状态越靠前
+    State,
+    /// 运行时长
+    Runtime,
+}
+
+/// 每个进程输出哪些线程
+#[derive(Debug, Clone, Args)]
+pub struct ThreadSelection {
+    /// 每个进程最多输出的线程数，0 表示不限制
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_THREAD_TOP)]
+    pub thread_top: usize,
+    /// 线程的排序依据
+    #[arg(long, value_enum, default_value_t = ThreadSortKey::Cpu)]
+    pub thread_top_by: ThreadSortKey,
+    /// 只输出这些状态的线程，例如 D 或 R，逗号分隔或多次指定
+    #[arg(long = "thread-state", value_name = "STATE", value_delimiter = ',')]
+    pub thread_states: Vec<char>,
+}
+
+impl Default for ThreadSelection {
+    fn default() -> Self {
+        ThreadSelection {
+            thread_top: DEFAULT_THREAD_TOP,
+            thread_top_by: ThreadSortKey::Cpu,
+            thread_states: Vec::new(),
+        }
+    }
+}
+
+impl ThreadSelection {
+    fn accepts(&self, state: char) -> bool {
+        self.thread_states.is_empty() || self.thread_states.contains(&state)
+    }
+}
+
+// 线程状态的排序优先级，数值越小越靠前
+fn thread_state_rank(state: char) -> u8 {
+    match state {
+        'D' => 0,
+        'R' => 1,
+        'Z' => 2,
+        'T' | 't' => 3,
+        'S' => 4,
+        'I' => 5,
+        _ => 6,
+    }
+}
+
+/// 进程数据的输出格式版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SchemaVersion {
+    /// 原有格式，线程的 CPU、运行时长与内存大小只输出显示字符串，内存按整数截断（例如 1G、45M），
+    /// 线程保留固定为 0.0 的 memoryUsage
+    ///
+    /// 新增的嵌套对象（memory、resources、io、activity）不影响已有字段，两个版本都会输出
+    #[default]
+    #[value(name = "1")]
+    V1,
+    /// 在显示字符串之外增加对应的数值字段，便于排序与聚合，内存显示保留一位小数，线程不再输出 memoryUsage
+    #[value(name = "2")]
+    V2,
+}
+
+impl SchemaVersion {
+    fn number(self) -> u32 {
+        match self {
+            SchemaVersion::V1 => 1,
+            SchemaVersion::V2 => 2,
+        }
+    }
+}
+
+/// 进程采集配置
+#[derive(Debug, Clone, Default, Args)]
+pub struct ProcessConfig {
+    #[command(flatten)]
+    pub filter: ProcessFilter,
+    /// 输出格式版本，默认 1 以兼容已有的数据消费方
+    #[arg(long, value_enum, value_name = "VERSION", default_value_t = SchemaVersion::V1)]
+    pub schema_version: SchemaVersion,
+    #[command(flatten)]
+    pub threads: ThreadSelection,
+    #[command(flatten)]
+    pub trend: TrendConfig,
+    #[command(flatten)]
+    pub thread_limits: ThreadLimitConfig,
+}
+
+/// 进程枚举结果
+struct ProcessEntry {
+    pid: u32,
+    ppid: u32,
+    /// 进程启动时间，与 pid 一起唯一标识进程（Linux 上为启动后的 tick 数）
+    start_time: u64,
+    name: String,
+    user_name: String,
+    status: String,
+    /// 常驻内存（字节）
+    memory: u64,
+    cpu_usage: f64,
+    thread_count: u32,
+    /// 磁盘 IO 速率，没有权限读取 io 或尚无基准数据时为空
+    io: Option<ProcessIo>,
+    /// 上下文切换与缺页速率，只对输出或参与排名的进程采样，尚无基准数据时为空
+    activity: Option<ActivityRates>,
+}
+
+/// (pid/tid, 启动时间) 对应的一组累计计数
+#[cfg(target_os = "linux")]
+type Counters<const N: usize> = HashMap<(u32, u64), [u64; N]>;
+
+/// 进程或线程累计计数的采样器
+///
+/// 按 (pid/tid, 启动时间) 记录 CPU tick、IO 字节数等累计计数，与上一次采样比较得到每秒增量，
+/// 启动时间不同说明 ID 已被复用，不与旧记录比较
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct CounterSampler<const N: usize> {
+    prev: Option<(Instant, Counters<N>)>,
+    now: Option<Instant>,
+    current: Counters<N>,
+}
+
+#[cfg(target_os = "linux")]
+impl<const N: usize> CounterSampler<N> {
+    fn is_primed(&self) -> bool {
+        self.prev.is_some()
+    }
+
+    /// 开始一轮采样
+    fn begin(&mut self) {
+        self.now = Some(Instant::now());
+        self.current.clear();
+    }
+
+    /// 记录本轮的累计计数，返回与上一轮之间每项的每秒增量，上一轮没有记录时返回 None
+    fn rates(&mut self, id: u32, start_time: u64, counters: [u64; N]) -> Option<[f64; N]> {
+        self.current.insert((id, start_time), counters);
+        let (Some(now), Some((prev_time, prev))) = (self.now, &self.prev) else {
+            return None;
+        };
+        let elapsed = now.duration_since(*prev_time).as_secs_f64();
+        let prev = prev.get(&(id, start_time)).filter(|_| elapsed > 0.0)?;
+        Some(std::array::from_fn(|i| counters[i].saturating_sub(prev[i]) as f64 / elapsed))
+    }
+
+    /// 结束本轮采样，本轮记录作为下一轮的基准（已退出的进程/线程随之丢弃）
+    fn finish(&mut self) {
+        if let Some(now) = self.now.take() {
+            self.prev = Some((now, std::mem::take(&mut self.current)));
+        }
+    }
+}
+
+/// 累计 CPU 时间（utime+stime，单位 tick）的采样器
+#[cfg(target_os = "linux")]
+type CpuTickSampler = CounterSampler<1>;
+
+#[cfg(target_os = "linux")]
+impl CpuTickSampler {
+    /// 记录本轮的累计 tick，返回与上一轮之间的 CPU 使用率（按单核计算，多线程进程可超过 100%），
+    /// 上一轮没有记录时返回 None
+    fn usage(&mut self, id: u32, start_time: u64, ticks: u64) -> Option<f64> {
+        self.rates(id, start_time, [ticks])
+            .map(|[rate]| util::round1(rate / procfs::ticks_per_second() as f64 * 100.0))
+    }
+}
+
+/// 进程的 voluntary_ctxt_switches、nonvoluntary_ctxt_switches、minflt、majflt 的采样器
+#[cfg(target_os = "linux")]
+type ActivitySampler = CounterSampler<4>;
+
+#[cfg(target_os = "linux")]
+impl ActivitySampler {
+    /// 采样给定进程的上下文切换与缺页速率，写入各进程的 activity
+    ///
+    /// 多线程进程的上下文切换需要读取每个线程的 status，因此只对输出或参与排名的进程采样
+    fn sample(&mut self, entries: &mut [ProcessEntry]) {
+        self.begin();
+        for entry in entries.iter_mut() {
+            entry.activity = open_process(entry.pid).ok()
+                .and_then(|proc| {
+                    let stat = proc.stat().ok()?;
+                    let counters = activity_counters(&stat, process_context_switches(&proc, &stat, proc.status().ok().as_ref()))?;
+                    self.rates(entry.pid, stat.starttime, counters)
+                })
+                .map(ActivityRates::from_rates);
+        }
+        self.finish();
+    }
+}
+
+impl ProcessEntry {
+    /// 磁盘读写速率之和（字节/秒）
+    fn io_rate(&self) -> f64 {
+        self.io.as_ref().map(|io| io.read_bytes_rate + io.write_bytes_rate).unwrap_or(0.0)
+    }
+
+    /// 主动与被动上下文切换速率之和（次/秒）
+    fn context_switch_rate(&self) -> f64 {
+        self.activity.as_ref()
+            .map(|activity| activity.voluntary_context_switch_rate + activity.nonvoluntary_context_switch_rate)
+            .unwrap_or(0.0)
+    }
+}
+
+/// 进程采集器，在多次采集之间保留各进程与线程的 CPU 时间以计算使用率
+///
+/// 只有首次采集需要等待一小段时间获取基准数据
+#[derive(Default)]
+pub struct ProcessCollector {
+    filter: ProcessFilter,
+    schema_version: SchemaVersion,
+    threads: ThreadSelection,
+    trends: TrendHistory,
+    thread_limits: ThreadLimitConfig,
+    lifecycle: LifecycleTracker,
     #[cfg(target_os = "linux")]
-    {
-        use procfs::process::Process;
-        let mut threads = Vec::new();
-        
-        if let Ok(proc) = Process::new(pid as i32) {
-            if let Ok(tasks) = proc.tasks() {
-                for task in tasks.flatten().take(10) {
-                    if let Ok(stat) = task.stat() {
-                        // 计算运行时间
-                        let ticks_per_sec = procfs::ticks_per_second() as u64;
-                        let uptime = if let Ok(boot_time) = procfs::boot_time_secs() {
-                            std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs() - boot_time
-                        } else {
-                            0
-                        };
-                        
-                        let start_time_secs = stat.starttime / ticks_per_sec;
-                        let runtime_secs = if uptime > start_time_secs {
-                            uptime - start_time_secs
-                        } else {
-                            0
-                        };
-                        
-                        let hours = runtime_secs / 3600;
-                        let minutes = (runtime_secs % 3600) / 60;
-                        let seconds = runtime_secs % 60;
-                        
-                        // 读取线程状态信息获取内存数据
-                        let (vsize, rss, shared) = if let Ok(status) = task.status() {
-                            (
-                                status.vmsize.unwrap_or(0) / 1024, // KB
-                                status.vmrss.unwrap_or(0),         // KB
-                                0, // 共享内存较难获取
-                            )
-                        } else {
-                            (0, 0, 0)
-                        };
-                        
-                        threads.push(ThreadData {
-                            thread_id: stat.pid as u32,
-                            user_name: process_user.to_string(),
-                            priority: stat.priority as u32,
-                            nice_value: stat.nice as i32,
-                            virtual_memory: format_memory(vsize),
-                            resident_memory: format_memory(rss),
-                            shared_memory: format_memory(shared),
-                            status: format!("{:?}", stat.state),
-                            cpu_usage: "0.0".to_string(), // 需要采样才能计算
-                            memory_usage: "0.0".to_string(),
-                            runtime: format!("{}:{:02}:{:02}", hours, minutes, seconds),
-                            command: stat.comm,
-                        });
-                    }
+    process_cpu: CpuTickSampler,
+    #[cfg(target_os = "linux")]
+    thread_cpu: CpuTickSampler,
+    /// 进程的 read_bytes、write_bytes、syscr、syscw、cancelled_write_bytes
+    #[cfg(target_os = "linux")]
+    process_io: CounterSampler<5>,
+    /// 输出进程的上下文切换与缺页
+    #[cfg(target_os = "linux")]
+    process_activity: ActivitySampler,
+    /// 排名进程的上下文切换与缺页，与输出进程分开记录，避免互相覆盖基准
+    #[cfg(target_os = "linux")]
+    ranking_activity: ActivitySampler,
+    /// 线程的 voluntary_ctxt_switches、nonvoluntary_ctxt_switches、minflt、majflt
+    #[cfg(target_os = "linux")]
+    thread_activity: CounterSampler<4>,
+    #[cfg(not(target_os = "linux"))]
+    sys: Option<sysinfo::System>,
+}
+
+impl ProcessCollector {
+    pub fn new(config: ProcessConfig) -> Self {
+        ProcessCollector {
+            filter: config.filter,
+            schema_version: config.schema_version,
+            threads: config.threads,
+            trends: TrendHistory::new(config.trend),
+            thread_limits: config.thread_limits,
+            ..Default::default()
+        }
+    }
+
+    fn is_primed(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            self.process_cpu.is_primed()
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.sys.is_some()
+        }
+    }
+
+    /// 首次采集前记录一次进程及其线程的基准数据
+    fn prime(&mut self) {
+        if self.is_primed() {
+            return;
+        }
+        let entries = self.list_processes();
+        // 此时进程 CPU 还没有基准，top 选出的进程与正式采集时可能不同，
+        // 因此为所有满足条件的进程记录线程基准，不做排序截取
+        let mut accepted: Vec<ProcessEntry> = entries.into_iter()
+            .filter(|process| self.filter.accepts(process))
+            .collect();
+        self.sample_activity(&mut accepted);
+        #[cfg(target_os = "linux")]
+        self.begin_thread_sampling();
+        for process in &accepted {
+            self.get_thread_details(process.pid, &process.user_name);
+        }
+        #[cfg(target_os = "linux")]
+        self.finish_thread_sampling();
+        if source::current().is_live() {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+    }
+
+    // 采样输出进程的上下文切换与缺页速率
+    fn sample_activity(&mut self, entries: &mut [ProcessEntry]) {
+        #[cfg(target_os = "linux")]
+        self.process_activity.sample(entries);
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = entries;
+    }
+
+    // 采样排名进程的上下文切换与缺页速率，首次排名时先记录一次基准
+    fn sample_ranking_activity(&mut self, entries: &mut [ProcessEntry]) {
+        #[cfg(target_os = "linux")]
+        {
+            if !self.ranking_activity.is_primed() {
+                self.ranking_activity.sample(entries);
+                if source::current().is_live() {
+                    std::thread::sleep(std::time::Duration::from_millis(200));
                 }
             }
+            self.ranking_activity.sample(entries);
         }
-        
-        threads
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = entries;
     }
-    
-    #[cfg(target_os = "macos")]
-    {
-        // macOS 线程信息获取较复杂，这里返回空数组
-        // 完整实现需要使用 task_threads 等底层API
-        let _ = (pid, process_user);
-        Vec::new()
+
+    #[cfg(target_os = "linux")]
+    fn begin_thread_sampling(&mut self) {
+        self.thread_cpu.begin();
+        self.thread_activity.begin();
     }
-    
-    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
-    {
-        let _ = (pid, process_user);
-        Vec::new()
+
+    #[cfg(target_os = "linux")]
+    fn finish_thread_sampling(&mut self) {
+        self.thread_cpu.finish();
+        self.thread_activity.finish();
+    }
+
+    // 获取进程的线程详细信息，所有线程都参与 CPU 与上下文切换、缺页采样，
+    // 按状态过滤、排序后输出前 thread_top 个
+    //
+    // 线程共享所属进程的地址空间，没有单独的内存占比，只在进程上输出一次
+    fn get_thread_details(&mut self, pid: u32, process_user: &str) -> Vec<ThreadData> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut threads = Vec::new();
+            let Ok(proc) = open_process(pid) else { return threads };
+            let Ok(tasks) = proc.tasks() else { return threads };
+
+            // 计算运行时间
+            let ticks_per_sec = procfs::ticks_per_second();
+            let uptime = uptime_secs();
+            let page_size = procfs::page_size();
+            let numeric = self.schema_version >= SchemaVersion::V2;
+
+            let mut candidates = Vec::new();
+            for task in tasks.flatten() {
+                let Ok(stat) = task.stat() else { continue };
+                let tid = stat.pid as u32;
+                let cpu_usage = self.thread_cpu.usage(tid, stat.starttime, stat.utime + stat.stime);
+                let activity = activity_counters(&stat, task.status().ok().as_ref().and_then(context_switches))
+                    .and_then(|counters| self.thread_activity.rates(tid, stat.starttime, counters))
+                    .map(ActivityRates::from_rates);
+                if self.threads.accepts(stat.state) {
+                    candidates.push((task, stat, cpu_usage, activity));
+                }
+            }
+
+            match self.threads.thread_top_by {
+                ThreadSortKey::Cpu => candidates.sort_by(|(_, a_stat, a_usage, _), (_, b_stat, b_usage, _)| {
+                    a_usage.is_none().cmp(&b_usage.is_none())
+                        .then(b_usage.unwrap_or(0.0).total_cmp(&a_usage.unwrap_or(0.0)))
+                        .then((b_stat.utime + b_stat.stime).cmp(&(a_stat.utime + a_stat.stime)))
+                }),
+                ThreadSortKey::Rss => candidates.sort_by_key(|(_, stat, _, _)| std::cmp::Reverse(stat.rss)),
+                ThreadSortKey::State => candidates.sort_by_key(|(_, stat, _, _)| thread_state_rank(stat.state)),
+                // 启动越早运行时间越长
+                ThreadSortKey::Runtime => candidates.sort_by_key(|(_, stat, _, _)| stat.starttime),
+            }
+            if self.threads.thread_top > 0 {
+                candidates.truncate(self.threads.thread_top);
+            }
+
+            for (task, stat, cpu_usage, activity) in candidates {
+                let start_time_secs = stat.starttime / ticks_per_sec;
+                let runtime_secs = uptime.saturating_sub(start_time_secs);
+
+                let hours = runtime_secs / 3600;
+                let minutes = (runtime_secs % 3600) / 60;
+                let seconds = runtime_secs % 60;
+
+                // 线程的 statm 与所属进程相同（共享地址空间），单位为页
+                let (vsize, rss, shared) = task.read::<procfs::process::StatM>("statm")
+                    .map(|statm| (statm.size * page_size, statm.resident * page_size, statm.shared * page_size))
+                    .unwrap_or((0, 0, 0));
+
+                threads.push(ThreadData {
+                    thread_id: stat.pid as u32,
+                    user_name: process_user.to_string(),
+                    priority: stat.priority as u32,
+                    nice_value: stat.nice as i32,
+                    virtual_memory: format_memory(vsize / 1024, self.schema_version),
+                    virtual_memory_bytes: numeric.then_some(vsize),
+                    resident_memory: format_memory(rss / 1024, self.schema_version),
+                    resident_memory_bytes: numeric.then_some(rss),
+                    shared_memory: format_memory(shared / 1024, self.schema_version),
+                    shared_memory_bytes: numeric.then_some(shared),
+                    status: format!("{:?}", stat.state),
+                    cpu_usage: format!("{:.1}", cpu_usage.unwrap_or(0.0)),
+                    cpu_usage_percent: cpu_usage.filter(|_| numeric),
+                    memory_usage: (!numeric).then(|| "0.0".to_string()),
+                    runtime: format!("{}:{:02}:{:02}", hours, minutes, seconds),
+                    runtime_seconds: numeric.then_some(runtime_secs),
+                    activity,
+                    kernel_wait: read_kernel_wait(pid, stat.pid as u32),
+                    command: stat.comm,
+                });
+            }
+
+            threads
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            // macOS 线程信息获取较复杂，这里返回空数组
+            // 完整实现需要使用 task_threads 等底层API
+            let _ = (pid, process_user);
+            Vec::new()
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        {
+            let _ = (pid, process_user);
+            Vec::new()
+        }
+    }
+
+    // 读取进程的命令行、可执行文件、工作目录、启动时间与会话信息
+    // 进程的完整命令行，读取失败时为空
+    fn process_cmdline(&self, pid: u32) -> String {
+        #[cfg(target_os = "linux")]
+        {
+            open_process(pid)
+                .and_then(|proc| proc.cmdline())
+                .map(|args| args.join(" "))
+                .unwrap_or_default()
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.sys.as_ref()
+                .and_then(|sys| sys.process(sysinfo::Pid::from_u32(pid)))
+                .map(|process| process.cmd().iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" "))
+                .unwrap_or_default()
+        }
+    }
+
+    fn process_details(&self, pid: u32) -> ProcessDetails {
+        #[cfg(target_os = "linux")]
+        {
+            let Ok(proc) = open_process(pid) else { return ProcessDetails::default() };
+            let stat = proc.stat().ok();
+            let ticks_per_sec = procfs::ticks_per_second();
+
+            ProcessDetails {
+                cmdline: proc.cmdline().map(|args| args.join(" ")).unwrap_or_default(),
+                // 没有权限或内核线程时读不到
+                exe: proc.exe().ok().map(|path| path.display().to_string()),
+                cwd: proc.cwd().ok().map(|path| path.display().to_string()),
+                start_time: stat.as_ref()
+                    .map(|stat| boot_time_secs() * 1000 + stat.starttime * 1000 / ticks_per_sec)
+                    .unwrap_or(0),
+                session: stat.as_ref().map(|stat| stat.session).unwrap_or(0),
+                tty: stat.as_ref().and_then(|stat| tty_name(stat.tty_nr())),
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let Some(process) = self.sys.as_ref().and_then(|sys| sys.process(sysinfo::Pid::from_u32(pid))) else {
+                return ProcessDetails::default();
+            };
+            ProcessDetails {
+                cmdline: process.cmd().iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                exe: process.exe().map(|path| path.display().to_string()),
+                cwd: process.cwd().map(|path| path.display().to_string()),
+                start_time: process.start_time() * 1000,
+                session: process.session_id().map(|sid| sid.as_u32() as i32).unwrap_or(0),
+                tty: None,
+            }
+        }
+    }
+
+    // 读取进程的 VSZ/RSS/共享内存（statm）及 PSS/USS/swap（smaps_rollup）
+    fn process_memory(&self, pid: u32) -> Option<ProcessMemory> {
+        #[cfg(target_os = "linux")]
+        {
+            let proc = open_process(pid).ok()?;
+            let statm = proc.statm().ok()?;
+            let page_size = procfs::page_size();
+
+            // smaps_rollup 需要 ptrace 权限，内核线程也没有
+            let rollup = proc.smaps_rollup().ok()
+                .and_then(|rollup| rollup.memory_map_rollup.0.into_iter().next())
+                .map(|map| map.extension.map);
+            let rollup_value = |keys: &[&str]| {
+                rollup.as_ref().map(|map| keys.iter().filter_map(|key| map.get(*key)).sum::<u64>())
+            };
+
+            Some(ProcessMemory::new(
+                self.schema_version,
+                statm.size * page_size,
+                statm.resident * page_size,
+                statm.shared * page_size,
+                rollup_value(&["Pss"]),
+                rollup_value(&["Private_Clean", "Private_Dirty", "Private_Hugetlb"]),
+                rollup_value(&["Swap"]),
+            ))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let process = self.sys.as_ref()?.process(sysinfo::Pid::from_u32(pid))?;
+            Some(ProcessMemory::new(self.schema_version, process.virtual_memory(), process.memory(), 0, None, None, None))
+        }
+    }
+
+    // 枚举所有进程，Linux 上按数据源读取 procfs
+    fn list_processes(&mut self) -> Vec<ProcessEntry> {
+        #[cfg(target_os = "linux")]
+        {
+            let Ok(all) = procfs::process::all_processes_with_root(source::current().proc_root()) else {
+                return Vec::new();
+            };
+            let page_size = procfs::page_size();
+            self.process_cpu.begin();
+            self.process_io.begin();
+
+            let entries = all.flatten()
+                .filter_map(|proc| {
+                    let stat = proc.stat().ok()?;
+                    let status = proc.status().ok();
+                    let uid = status.as_ref().map(|status| status.ruid);
+                    let pid = stat.pid as u32;
+                    // io 需要 ptrace 权限
+                    let io = proc.io().ok()
+                        .and_then(|io| self.process_io.rates(
+                            pid,
+                            stat.starttime,
+                            [io.read_bytes, io.write_bytes, io.syscr, io.syscw, io.cancelled_write_bytes],
+                        ))
+                        .map(ProcessIo::from_rates);
+                    Some(ProcessEntry {
+                        pid,
+                        ppid: stat.ppid as u32,
+                        start_time: stat.starttime,
+                        name: stat.comm.clone(),
+                        user_name: uid.and_then(user_name_of).unwrap_or_else(|| "unknown".to_string()),
+                        status: process_status_name(stat.state),
+                        memory: stat.rss * page_size,
+                        cpu_usage: self.process_cpu.usage(pid, stat.starttime, stat.utime + stat.stime).unwrap_or(0.0),
+                        thread_count: stat.num_threads as u32,
+                        io,
+                        activity: None,
+                    })
+                })
+                .collect();
+
+            self.process_cpu.finish();
+            self.process_io.finish();
+            entries
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            use sysinfo::{ProcessRefreshKind, ProcessesToUpdate};
+
+            // 保留同一个 System，sysinfo 据此计算两次刷新之间的 CPU 使用率
+            let sys = self.sys.get_or_insert_with(sysinfo::System::new);
+            sys.refresh_memory();
+            sys.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::everything());
+
+            sys.processes().iter()
+                .map(|(pid, process)| ProcessEntry {
+                    pid: pid.as_u32(),
+                    ppid: process.parent().map(|ppid| ppid.as_u32()).unwrap_or(0),
+                    start_time: process.start_time(),
+                    name: process.name().to_string_lossy().to_string(),
+                    user_name: process.user_id()
+                        .and_then(|uid| {
+                            #[cfg(unix)]
+                            {
+                                user_name_of(**uid)
+                            }
+                            #[cfg(not(unix))]
+                            {
+                                Some(uid.to_string())
+                            }
+                        })
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    status: format!("{:?}", process.status()),
+                    memory: process.memory(),
+                    cpu_usage: process.cpu_usage() as f64,
+                    thread_count: get_thread_count(pid.as_u32()),
+                    // 非 Linux 平台暂不采集 IO 与上下文切换速率
+                    io: None,
+                    activity: None,
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(unix)]
+fn user_name_of(uid: u32) -> Option<String> {
+    users::get_user_by_uid(uid).map(|user| user.name().to_string_lossy().to_string())
+}
+
+// 与 sysinfo 的 ProcessStatus 保持相同的名称，兼容已有的数据
+#[cfg(target_os = "linux")]
+fn process_status_name(state: char) -> String {
+    match state {
+        'R' => "Run",
+        'S' => "Sleep",
+        'I' => "Idle",
+        'D' => "UninterruptibleDiskSleep",
+        'Z' => "Zombie",
+        'T' => "Stop",
+        't' => "Tracing",
+        'X' | 'x' => "Dead",
+        'K' => "Wakekill",
+        'W' => "Waking",
+        'P' => "Parked",
+        other => return format!("Unknown({})", other as u32),
+    }
+    .to_string()
+}
+
+// 系统启动时刻（Unix 时间戳，秒），取自 /proc/stat 的 btime
+#[cfg(target_os = "linux")]
+fn boot_time_secs() -> u64 {
+    source::current().read_proc("stat")
+        .ok()
+        .and_then(|content| {
+            content.lines()
+                .find_map(|line| line.strip_prefix("btime "))
+                .and_then(|value| value.trim().parse().ok())
+        })
+        .unwrap_or(0)
+}
+
+// 将 stat 中的控制终端设备号转换为 pts/0、tty1 这样的名称，没有控制终端时为空
+#[cfg(target_os = "linux")]
+fn tty_name((major, minor): (i32, i32)) -> Option<String> {
+    match major {
+        0 => None,
+        4 if minor < 64 => Some(format!("tty{}", minor)),
+        4 => Some(format!("ttyS{}", minor - 64)),
+        136..=143 => Some(format!("pts/{}", (major - 136) * 256 + minor)),
+        _ => Some(format!("{}:{}", major, minor)),
     }
 }
 
-// 格式化内存大小
+// 系统启动后经过的秒数
 #[cfg(target_os = "linux")]
-fn format_memory(kb: u64) -> String {
-    if kb >= 1024 * 1024 {
-        format!("{}G", kb / (1024 * 1024))
-    } else if kb >= 1024 {
-        format!("{}M", kb / 1024)
-    } else {
-        format!("{}K", kb)
+fn uptime_secs() -> u64 {
+    source::current().read_proc("uptime")
+        .ok()
+        .and_then(|content| content.split_whitespace().next()?.parse::<f64>().ok())
+        .map(|secs| secs as u64)
+        .unwrap_or(0)
+}
+
+/// 格式化内存大小
+///
+/// 版本 1 按整数截断（例如 1G、45M），版本 2 起 G 与 M 保留一位小数，整数时省略小数部分（例如 1.9G、45M）
+pub fn format_memory(kb: u64, version: SchemaVersion) -> String {
+    let scaled = |value: f64, unit: &str| {
+        let text = format!("{:.1}", value);
+        format!("{}{}", text.strip_suffix(".0").unwrap_or(&text), unit)
+    };
+    match version {
+        SchemaVersion::V1 if kb >= 1024 * 1024 => format!("{}G", kb / (1024 * 1024)),
+        SchemaVersion::V1 if kb >= 1024 => format!("{}M", kb / 1024),
+        _ if kb >= 1024 * 1024 => scaled(kb as f64 / (1024.0 * 1024.0), "G"),
+        _ if kb >= 1024 => scaled(kb as f64 / 1024.0, "M"),
+        _ => format!("{}K", kb),
     }
 }
 
@@ -153,17 +854,234 @@ pub struct ProcessData {
     pub server_os: String,
     #[serde(rename = "serverStatus")]
     pub server_status: String,
+    /// 输出格式版本，版本 1 时省略
+    #[serde(rename = "schemaVersion", default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
     pub pid: u32,
+    #[serde(default)]
+    pub ppid: u32,
     pub name: String,
+    #[serde(flatten)]
+    pub details: ProcessDetails,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<ProcessMemory>,
+    /// 文件描述符与资源限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ProcessResources>,
+    /// 磁盘 IO 速率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io: Option<ProcessIo>,
+    /// 上下文切换与缺页速率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<ActivityRates>,
     #[serde(rename = "userName")]
     pub user_name: String,
     pub status: String,
     pub timestamp: u64,
+    /// 常驻内存占系统内存的百分比 (%)，由进程的所有线程共享
+    #[serde(rename = "memoryUsage", default)]
+    pub memory_usage: f64,
     pub trend: Vec<TrendData>,
+    /// 进程的线程总数
+    #[serde(rename = "threadCount", default)]
+    pub thread_count: u32,
+    /// threads 中输出的线程数
+    #[serde(rename = "selectedThreadCount", default)]
+    pub selected_thread_count: u32,
     pub threads: Vec<ThreadData>,
 }
 
+/// monitor 每轮输出的进程相关数据
+#[derive(Debug, Serialize)]
+pub struct ProcessTick {
+    pub process: Vec<ProcessData>,
+    #[serde(rename = "threadLimits")]
+    pub thread_limits: ThreadLimitReport,
+    #[serde(rename = "processEvents")]
+    pub process_events: Vec<ProcessEvent>,
+}
+
+/// 进程树节点
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessTreeNode {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    pub status: String,
+    #[serde(rename = "cpuUsage")]
+    pub cpu_usage: f64,
+    /// 常驻内存（字节）
+    #[serde(rename = "memoryBytes")]
+    pub memory_bytes: u64,
+    #[serde(rename = "memoryUsage")]
+    pub memory_usage: f64,
+    #[serde(rename = "threadCount")]
+    pub thread_count: u32,
+    /// 包含自身在内的整个子树合计
+    pub subtree: SubtreeTotals,
+    pub children: Vec<ProcessTreeNode>,
+}
+
+/// 子树合计，内存为各进程 RSS 之和（共享页会被重复计算）
 #[derive(Debug, Serialize, Deserialize)]
+pub struct SubtreeTotals {
+    #[serde(rename = "processCount")]
+    pub process_count: u32,
+    #[serde(rename = "cpuUsage")]
+    pub cpu_usage: f64,
+    #[serde(rename = "memoryBytes")]
+    pub memory_bytes: u64,
+    #[serde(rename = "memoryUsage")]
+    pub memory_usage: f64,
+    #[serde(rename = "threadCount")]
+    pub thread_count: u32,
+}
+
+/// 进程的完整命令行、路径与会话信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessDetails {
+    /// 完整命令行，参数以空格分隔，内核线程为空
+    #[serde(default)]
+    pub cmdline: String,
+    /// 可执行文件路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe: Option<String>,
+    /// 当前工作目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// 启动时间戳（毫秒）
+    #[serde(rename = "startTime", default)]
+    pub start_time: u64,
+    /// 会话 ID
+    #[serde(default)]
+    pub session: i32,
+    /// 控制终端，例如 pts/0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tty: Option<String>,
+}
+
+/// 进程内存统计，每项同时提供字节数与格式化字符串
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessMemory {
+    /// 虚拟内存（VSZ）
+    #[serde(rename = "virtualMemory")]
+    pub virtual_memory: String,
+    #[serde(rename = "virtualMemoryBytes")]
+    pub virtual_memory_bytes: u64,
+    /// 常驻内存（RSS）
+    #[serde(rename = "residentMemory")]
+    pub resident_memory: String,
+    #[serde(rename = "residentMemoryBytes")]
+    pub resident_memory_bytes: u64,
+    /// 常驻内存中的共享部分（文件映射与共享内存）
+    #[serde(rename = "sharedMemory")]
+    pub shared_memory: String,
+    #[serde(rename = "sharedMemoryBytes")]
+    pub shared_memory_bytes: u64,
+    /// 共享页按映射进程数均摊后的内存（PSS），无权限读取 smaps_rollup 时为空
+    #[serde(rename = "pssMemory", default, skip_serializing_if = "Option::is_none")]
+    pub pss_memory: Option<String>,
+    #[serde(rename = "pssMemoryBytes", default, skip_serializing_if = "Option::is_none")]
+    pub pss_memory_bytes: Option<u64>,
+    /// 进程独占的内存（USS），即进程退出后可释放的内存
+    #[serde(rename = "ussMemory", default, skip_serializing_if = "Option::is_none")]
+    pub uss_memory: Option<String>,
+    #[serde(rename = "ussMemoryBytes", default, skip_serializing_if = "Option::is_none")]
+    pub uss_memory_bytes: Option<u64>,
+    /// 被换出到 swap 的内存
+    #[serde(rename = "swapMemory", default, skip_serializing_if = "Option::is_none")]
+    pub swap_memory: Option<String>,
+    #[serde(rename = "swapMemoryBytes", default, skip_serializing_if = "Option::is_none")]
+    pub swap_memory_bytes: Option<u64>,
+}
+
+impl ProcessMemory {
+    fn new(version: SchemaVersion, vsz: u64, rss: u64, shared: u64, pss: Option<u64>, uss: Option<u64>, swap: Option<u64>) -> Self {
+        let format = |bytes: u64| format_memory(bytes / 1024, version);
+        ProcessMemory {
+            virtual_memory: format(vsz),
+            virtual_memory_bytes: vsz,
+            resident_memory: format(rss),
+            resident_memory_bytes: rss,
+            shared_memory: format(shared),
+            shared_memory_bytes: shared,
+            pss_memory: pss.map(format),
+            pss_memory_bytes: pss,
+            uss_memory: uss.map(format),
+            uss_memory_bytes: uss,
+            swap_memory: swap.map(format),
+            swap_memory_bytes: swap,
+        }
+    }
+}
+
+/// 进程在两次采集之间的磁盘 IO 速率，取自 /proc/<pid>/io
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessIo {
+    /// 实际从存储设备读取的速率（字节/秒），不含页缓存命中
+    #[serde(rename = "readBytesRate")]
+    pub read_bytes_rate: f64,
+    /// 写入页缓存、之后需要落盘的速率（字节/秒）
+    #[serde(rename = "writeBytesRate")]
+    pub write_bytes_rate: f64,
+    /// read 类系统调用次数（次/秒）
+    #[serde(rename = "readSyscallRate")]
+    pub read_syscall_rate: f64,
+    /// write 类系统调用次数（次/秒）
+    #[serde(rename = "writeSyscallRate")]
+    pub write_syscall_rate: f64,
+    /// 落盘前被截断或删除而不再需要写入的速率（字节/秒）
+    #[serde(rename = "cancelledWriteBytesRate")]
+    pub cancelled_write_bytes_rate: f64,
+}
+
+impl ProcessIo {
+    #[cfg(target_os = "linux")]
+    fn from_rates([read_bytes, write_bytes, syscr, syscw, cancelled_write_bytes]: [f64; 5]) -> Self {
+        ProcessIo {
+            read_bytes_rate: util::round1(read_bytes),
+            write_bytes_rate: util::round1(write_bytes),
+            read_syscall_rate: util::round1(syscr),
+            write_syscall_rate: util::round1(syscw),
+            cancelled_write_bytes_rate: util::round1(cancelled_write_bytes),
+        }
+    }
+}
+
+/// 进程或线程在两次采集之间的上下文切换与缺页速率
+///
+/// 锁竞争表现为主动切换增多，CPU 争抢表现为被动切换增多，主缺页需要从磁盘读取页面
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityRates {
+    /// 主动让出 CPU（等待锁、IO 等）的次数（次/秒）
+    #[serde(rename = "voluntaryContextSwitchRate")]
+    pub voluntary_context_switch_rate: f64,
+    /// 时间片用完被抢占的次数（次/秒）
+    #[serde(rename = "nonvoluntaryContextSwitchRate")]
+    pub nonvoluntary_context_switch_rate: f64,
+    /// 次缺页（次/秒）
+    #[serde(rename = "minorFaultRate")]
+    pub minor_fault_rate: f64,
+    /// 主缺页（次/秒）
+    #[serde(rename = "majorFaultRate")]
+    pub major_fault_rate: f64,
+}
+
+impl ActivityRates {
+    #[cfg(target_os = "linux")]
+    fn from_rates([voluntary, nonvoluntary, minor_faults, major_faults]: [f64; 4]) -> Self {
+        ActivityRates {
+            voluntary_context_switch_rate: util::round1(voluntary),
+            nonvoluntary_context_switch_rate: util::round1(nonvoluntary),
+            minor_fault_rate: util::round1(minor_faults),
+            major_fault_rate: util::round1(major_faults),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrendData {
     pub timestamp: u64,
     #[serde(rename = "cpuUsage")]
@@ -185,151 +1103,833 @@ pub struct ThreadData {
     pub nice_value: i32,
     #[serde(rename = "virtualMemory")]
     pub virtual_memory: String,
+    /// 虚拟内存（字节），schema 版本 2 起输出
+    #[serde(rename = "virtualMemoryBytes", default, skip_serializing_if = "Option::is_none")]
+    pub virtual_memory_bytes: Option<u64>,
     #[serde(rename = "residentMemory")]
     pub resident_memory: String,
+    /// 常驻内存（字节），schema 版本 2 起输出
+    #[serde(rename = "residentMemoryBytes", default, skip_serializing_if = "Option::is_none")]
+    pub resident_memory_bytes: Option<u64>,
     #[serde(rename = "sharedMemory")]
     pub shared_memory: String,
+    /// 共享内存（字节），schema 版本 2 起输出
+    #[serde(rename = "sharedMemoryBytes", default, skip_serializing_if = "Option::is_none")]
+    pub shared_memory_bytes: Option<u64>,
     pub status: String,
     #[serde(rename = "cpuUsage")]
     pub cpu_usage: String,
-    #[serde(rename = "memoryUsage")]
-    pub memory_usage: String,
+    /// CPU 使用率 (%)，schema 版本 2 起输出，线程尚无上一次采样（如所属进程刚被选中）时为空
+    #[serde(rename = "cpuUsagePercent", default, skip_serializing_if = "Option::is_none")]
+    pub cpu_usage_percent: Option<f64>,
+    /// 线程共享进程的内存，没有单独的内存占比（见进程的 memoryUsage），
+    /// 为兼容只在 schema 版本 1 中输出，固定为 0.0
+    #[serde(rename = "memoryUsage", default, skip_serializing_if = "Option::is_none")]
+    pub memory_usage: Option<String>,
     pub runtime: String,
+    /// 运行时长（秒），schema 版本 2 起输出
+    #[serde(rename = "runtimeSeconds", default, skip_serializing_if = "Option::is_none")]
+    pub runtime_seconds: Option<u64>,
+    /// 上下文切换与缺页速率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<ActivityRates>,
+    #[serde(flatten)]
+    pub kernel_wait: KernelWaitInfo,
     pub command: String,
 }
 
-pub fn collect_processes() -> Result<String, Box<dyn std::error::Error>> {
-    // 初始化系统信息
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    
-    // 生成服务器ID
-    let server_id = util::generate_server_id();
+/// 线程在内核中等待的位置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KernelWaitInfo {
+    /// 线程阻塞所在的内核函数，未阻塞时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wchan: Option<String>,
+    /// 正在执行的系统调用号及参数，在用户态运行时为 running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub syscall: Option<String>,
+    /// 内核调用栈，需要 root 权限
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+}
 
-    // 获取当前时间戳
-    let current_timestamp = Utc::now().timestamp_millis() as u64;
+/// 线程栈导出中的单个线程
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThreadStack {
+    #[serde(rename = "threadId")]
+    pub thread_id: u32,
+    pub command: String,
+    pub status: String,
+    #[serde(flatten)]
+    pub kernel_wait: KernelWaitInfo,
+}
 
-    // 获取系统总内存
-    let total_memory = sys.total_memory() as f64;
+/// 系统总内存（字节）
+fn total_memory() -> u64 {
+    #[cfg(target_os = "linux")]
+    {
+        crate::meminfo::read_memory_detail().map(|detail| detail.total).unwrap_or(0)
+    }
 
-    // 获取服务器信息
-    let server_name = hostname::get()
-        .map(|h| h.to_string_lossy().to_string())
-        .unwrap_or_else(|_| "unknown".to_string());
+    #[cfg(not(target_os = "linux"))]
+    {
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        sys.total_memory()
+    }
+}
 
-    let server_ip = local_ip()
-        .map(|ip| ip.to_string())
-        .unwrap_or_else(|_| "unknown".to_string());
+/// 服务器主机名，读取目录快照时使用快照中的主机名
+fn server_name() -> String {
+    let source = source::current();
+    if !source.is_live()
+        && let Ok(name) = source.read_proc("sys/kernel/hostname")
+    {
+        return name.trim().to_string();
+    }
+    hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
 
-    let server_os = Command::new("uname")
+/// 操作系统信息，读取目录快照时由快照中的内核信息拼出与 uname -a 类似的内容
+fn server_os() -> String {
+    let source = source::current();
+    if !source.is_live() {
+        let read = |name: &str| source.read_proc(format!("sys/kernel/{}", name))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default();
+        return format!("{} {} {} {}", read("ostype"), read("hostname"), read("osrelease"), read("version"))
+            .trim()
+            .to_string();
+    }
+    Command::new("uname")
         .arg("-a")
         .output()
         .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
-        .unwrap_or_else(|_| "unknown".to_string());
+        .unwrap_or_else(|_| "unknown".to_string())
+}
 
-    // 收集所有进程信息
-    let mut processes = Vec::new();
-    
-    for (pid, process) in sys.processes() {
-        let process_name = process.name().to_string_lossy().to_string();
-
-        // 获取用户名
-        let user_name = process.user_id()
-            .and_then(|uid| {
-                #[cfg(unix)]
-                {
-                    use users::get_user_by_uid;
-                    get_user_by_uid(**uid).map(|user| user.name().to_string_lossy().to_string())
-                }
-                #[cfg(not(unix))]
-                {
-                    Some(uid.to_string())
-                }
-            })
-            .unwrap_or_else(|| "unknown".to_string());
+impl ProcessCollector {
+    pub fn collect(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.prime();
+        let entries = self.list_processes();
+        let processes = self.process_data(entries);
+
+        // 序列化为JSON字符串（格式化输出）
+        let json_string = serde_json::to_string_pretty(&processes)?;
         
-        // 获取进程状态
-        let status = format!("{:?}", process.status());
+        Ok(json_string)
+    }
+
+    /// monitor 每轮的进程数据、线程耗尽预警与生命周期事件，三者共用同一次进程枚举
+    pub fn collect_tick(&mut self) -> ProcessTick {
+        self.prime();
+        let entries = self.list_processes();
+        ProcessTick {
+            thread_limits: self.thread_limit_report(&entries),
+            process_events: self.process_events(&entries),
+            process: self.process_data(entries),
+        }
+    }
+
+    // 为选中的进程生成完整数据，并更新趋势历史与线程采样
+    fn process_data(&mut self, entries: Vec<ProcessEntry>) -> Vec<ProcessData> {
+        // 生成服务器ID
+        let server_id = util::generate_server_id();
+
+        // 获取当前时间戳
+        let current_timestamp = Utc::now().timestamp_millis() as u64;
+
+        // 获取系统总内存
+        let total_memory = total_memory() as f64;
+
+        // 获取服务器信息
+        let server_name = server_name();
+
+        let server_ip = util::server_ip();
+
+        let server_os = server_os();
+
+        // 收集选中的进程信息
+        let mut processes = Vec::new();
+        #[cfg(target_os = "linux")]
+        self.begin_thread_sampling();
+
+        // 丢弃已退出进程的趋势历史
+        let alive: HashSet<(u32, u64)> = entries.iter()
+            .map(|process| (process.pid, process.start_time))
+            .collect();
+        self.trends.retain(|pid, start_time| alive.contains(&(pid, start_time)));
+
+        let user_tasks = user_task_counts(&entries);
         
-        // 计算内存使用百分比
-        let memory_percentage = if total_memory > 0.0 {
-            (process.memory() as f64 / total_memory) * 100.0
+        let mut selected = self.filter.select(entries);
+        self.sample_activity(&mut selected);
+
+        for process in selected {
+            // 计算内存使用百分比
+            let memory_percentage = if total_memory > 0.0 {
+                (process.memory as f64 / total_memory) * 100.0
+            } else {
+                0.0
+            };
+            
+            // 记录当前快照，输出保留的趋势历史
+            let trend = self.trends.record(process.pid, process.start_time, TrendData {
+                timestamp: current_timestamp,
+                cpu_usage: process.cpu_usage,
+                memory_usage: memory_percentage,
+                thread_count: process.thread_count,
+            });
+            
+            // 获取线程详细信息
+            let threads = self.get_thread_details(process.pid, &process.user_name);
+            
+            // 创建进程数据
+            let process_data = ProcessData {
+                server_id: server_id.clone(),
+                server_name: server_name.clone(),
+                server_ip: server_ip.clone(),
+                server_os: server_os.clone(),
+                server_status: "running".to_string(),
+                schema_version: (self.schema_version > SchemaVersion::V1).then(|| self.schema_version.number()),
+                pid: process.pid,
+                ppid: process.ppid,
+                name: process.name,
+                details: self.process_details(process.pid),
+                memory: self.process_memory(process.pid),
+                resources: resources::read_process_resources(
+                    process.pid,
+                    user_tasks.get(&process.user_name).copied().unwrap_or(0),
+                ),
+                io: process.io,
+                activity: process.activity,
+                user_name: process.user_name,
+                status: process.status,
+                timestamp: current_timestamp,
+                memory_usage: util::round1(memory_percentage),
+                trend,
+                thread_count: process.thread_count,
+                selected_thread_count: threads.len() as u32,
+                threads,
+            };
+            
+            processes.push(process_data);
+        }
+        #[cfg(target_os = "linux")]
+        self.finish_thread_sampling();
+
+        processes
+    }
+
+    /// 构建进程树，每个节点附带整个子树的 CPU、内存与线程合计
+    ///
+    /// 未指定名称、用户、PID 或 top 条件时输出完整的进程树，
+    /// 否则以选中的进程为根（已在其他选中进程子树中的除外）
+    pub fn collect_tree(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.prime();
+
+        let total_memory = total_memory() as f64;
+        let entries = self.list_processes();
+
+        let index_of: HashMap<u32, usize> = entries.iter()
+            .enumerate()
+            .map(|(index, process)| (process.pid, index))
+            .collect();
+        let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (index, process) in entries.iter().enumerate() {
+            if process.ppid != process.pid {
+                children.entry(process.ppid).or_default().push(index);
+            }
+        }
+
+        let roots: Vec<usize> = if self.filter.has_selector() {
+            let selected = self.filter.select_indices(&entries);
+            let selected_pids: HashSet<u32> = selected.iter().map(|&index| entries[index].pid).collect();
+            selected.into_iter()
+                .filter(|&index| !has_selected_ancestor(&entries, &index_of, &selected_pids, index))
+                .collect()
         } else {
-            0.0
+            (0..entries.len())
+                .filter(|&index| {
+                    let process = &entries[index];
+                    process.ppid == process.pid || !index_of.contains_key(&process.ppid)
+                })
+                .collect()
         };
-        
-        // 获取线程数
-        let thread_count = get_thread_count(pid.as_u32());
-        
-        // 跳过线程数少于20的进程
-        if thread_count < 20 {
-            continue;
+
+        let tree: Vec<ProcessTreeNode> = roots.into_iter()
+            .map(|index| build_tree_node(&entries, &children, index, total_memory))
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&tree)?)
+    }
+
+    /// 对比线程数与 kernel.threads-max、kernel.pid_max、cgroup 的 pids.max 及 RLIMIT_NPROC，
+    /// 在达到限制之前给出预警
+    pub fn check_thread_limits(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let entries = self.list_processes();
+        Ok(serde_json::to_string_pretty(&self.thread_limit_report(&entries))?)
+    }
+
+    fn thread_limit_report(&self, entries: &[ProcessEntry]) -> ThreadLimitReport {
+        let config = &self.thread_limits;
+        let mut warnings = Vec::new();
+
+        // 每个线程都占用一个 pid，因此线程总数同时受 threads-max 与 pid_max 限制
+        let total_threads: u64 = entries.iter().map(|process| process.thread_count as u64).sum();
+        let threads_max = thread_limits::read_kernel_limit("threads-max");
+        let pid_max = thread_limits::read_kernel_limit("pid_max");
+        warnings.extend(threads_max.and_then(|max| config.check("system", "threads-max", total_threads, max)));
+        warnings.extend(pid_max.and_then(|max| config.check("system", "pid_max", total_threads, max)));
+
+        // pids.max 对整个子树生效，逐级检查进程所在的 cgroup 及其祖先
+        let cgroup_dirs: BTreeSet<_> = entries.iter()
+            .filter_map(|process| cgroup::cgroup_dir_of(&process.pid.to_string()))
+            .flat_map(|dir| cgroup::with_ancestors(&dir))
+            .collect();
+        for dir in cgroup_dirs {
+            let pids = cgroup::read_limit(&dir, "pids.current", "pids.max");
+            if let Some(max) = pids.limit
+                && let Some(mut warning) = config.check("cgroup", "pids.max", pids.current, max)
+            {
+                warning.cgroup = Some(cgroup::display_path(&dir));
+                warnings.push(warning);
+            }
+        }
+
+        // RLIMIT_NPROC 限制的是同一用户的线程总数，超出时该进程的 pthread_create 失败
+        // （root 不受此限制）。每个用户只读取并检查线程最多的进程的限制
+        let user_tasks = user_task_counts(entries);
+        let mut by_threads: Vec<&ProcessEntry> = entries.iter()
+            .filter(|process| process.user_name != "root")
+            .collect();
+        by_threads.sort_by_key(|process| std::cmp::Reverse(process.thread_count));
+        let mut checked = HashSet::new();
+        for process in by_threads {
+            if !checked.insert(process.user_name.as_str()) {
+                continue;
+            }
+            let Some(max) = resources::read_nproc_limit(process.pid) else { continue };
+            let current = user_tasks.get(&process.user_name).copied().unwrap_or(0);
+            if let Some(mut warning) = config.check("process", "RLIMIT_NPROC", current, max) {
+                warning.message = format!(
+                    "{}[{}] has {} threads, user {} has {} threads, {:.1}% of RLIMIT_NPROC ({})",
+                    process.name, process.pid, process.thread_count, process.user_name, current, warning.usage, max,
+                );
+                warning.pid = Some(process.pid);
+                warning.name = Some(process.name.clone());
+                warning.user_name = Some(process.user_name.clone());
+                warnings.push(warning);
+            }
+        }
+
+        ThreadLimitReport {
+            timestamp: Utc::now().timestamp_millis() as u64,
+            total_threads,
+            threads_max,
+            pid_max,
+            warnings,
         }
-        
-        // 创建趋势数据（当前快照）
-        let trend = vec![TrendData {
-            timestamp: current_timestamp,
-            cpu_usage: process.cpu_usage() as f64,
-            memory_usage: memory_percentage,
-            thread_count,
-        }];
-        
-        // 获取线程详细信息
-        let threads = get_thread_details(pid.as_u32(), &user_name);
-        
-        // 创建进程数据
-        let process_data = ProcessData {
-            server_id: server_id.clone(),
-            server_name: server_name.clone(),
-            server_ip: server_ip.clone(),
-            server_os: server_os.clone(),
-            server_status: "running".to_string(),
-            pid: pid.as_u32(),
-            name: process_name,
-            user_name,
-            status,
-            timestamp: current_timestamp,
-            trend,
-            threads,
-        };
-        
-        processes.push(process_data);
     }
 
-    // 序列化为JSON字符串（格式化输出）
-    let json_string = serde_json::to_string_pretty(&processes)?;
-    
-    Ok(json_string)
+    // 对比上次调用以来的进程集合，返回进程启动、退出与重启事件，首次调用只记录基准
+    //
+    // 跟踪按 PID、用户与名称条件匹配的进程（线程数与 top 条件会随时间变化，不参与），
+    // 内核线程不跟踪
+    fn process_events(&mut self, entries: &[ProcessEntry]) -> Vec<ProcessEvent> {
+        let timestamp = Utc::now().timestamp_millis() as u64;
+        let observed: Vec<ObservedProcess> = entries.iter()
+            .filter(|process| !is_kernel_thread(process) && self.filter.matches_identity(process))
+            .map(|process| ObservedProcess {
+                pid: process.pid,
+                start_time: process.start_time,
+                name: process.name.clone(),
+                user_name: process.user_name.clone(),
+            })
+            .collect();
+
+        // 首次调用时所有进程都是新进程，只读取命令行，启动时间由枚举得到的启动 tick 换算
+        #[cfg(target_os = "linux")]
+        let (boot_time, ticks_per_sec) = (boot_time_secs(), procfs::ticks_per_second());
+        let mut lifecycle = std::mem::take(&mut self.lifecycle);
+        let events = lifecycle.update(timestamp, observed, |process| {
+            #[cfg(target_os = "linux")]
+            let started_at = boot_time * 1000 + process.start_time * 1000 / ticks_per_sec;
+            #[cfg(not(target_os = "linux"))]
+            let started_at = process.start_time * 1000;
+            (self.process_cmdline(process.pid), started_at)
+        });
+        self.lifecycle = lifecycle;
+        events
+    }
+
+    /// 按指定依据对进程降序排名，返回前 top 个，top 为 0 时返回全部
+    pub fn rank(&mut self, key: RankKey, top: usize) -> ProcessRanking {
+        self.rank_matching(key, top, |_| true)
+    }
+
+    // 在满足选择条件且 keep 返回 true 的进程中排名
+    fn rank_matching(&mut self, key: RankKey, top: usize, keep: impl Fn(&ProcessEntry) -> bool) -> ProcessRanking {
+        self.prime();
+
+        let mut entries: Vec<ProcessEntry> = self.list_processes()
+            .into_iter()
+            .filter(|process| self.filter.accepts_for_ranking(process) && keep(process))
+            .collect();
+
+        // 按上下文切换排名时采样所有候选进程，否则只采样输出的进程
+        if key == RankKey::ContextSwitches {
+            self.sample_ranking_activity(&mut entries);
+        }
+
+        // 按 fd 数排名时统计所有进程，否则只统计输出的进程
+        let mut fd_counts: HashMap<u32, Option<u64>> = HashMap::new();
+        match key {
+            RankKey::Threads => entries.sort_by_key(|process| std::cmp::Reverse(process.thread_count)),
+            RankKey::Cpu => entries.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+            RankKey::Rss => entries.sort_by_key(|process| std::cmp::Reverse(process.memory)),
+            RankKey::Fds => {
+                fd_counts = entries.iter().map(|process| (process.pid, resources::count_fds(process.pid))).collect();
+                entries.sort_by_key(|process| std::cmp::Reverse(fd_counts[&process.pid]));
+            }
+            // 没有权限读取 IO 的进程排在最后，而不是按 0 参与排名
+            RankKey::Io => entries.sort_by(|a, b| {
+                a.io.is_none().cmp(&b.io.is_none()).then(b.io_rate().total_cmp(&a.io_rate()))
+            }),
+            RankKey::ContextSwitches => entries.sort_by(|a, b| b.context_switch_rate().total_cmp(&a.context_switch_rate())),
+        }
+        if top > 0 {
+            entries.truncate(top);
+        }
+        if key != RankKey::ContextSwitches {
+            self.sample_ranking_activity(&mut entries);
+        }
+
+        let processes = entries.into_iter()
+            .enumerate()
+            .map(|(index, process)| RankedProcess {
+                rank: index + 1,
+                pid: process.pid,
+                fd_count: fd_counts.get(&process.pid).copied().unwrap_or_else(|| resources::count_fds(process.pid)),
+                io_rate: util::round1(process.io_rate()),
+                context_switch_rate: util::round1(process.context_switch_rate()),
+                name: process.name,
+                user_name: process.user_name,
+                thread_count: process.thread_count,
+                cpu_usage: process.cpu_usage,
+                memory_bytes: process.memory,
+                io: process.io,
+            })
+            .collect();
+
+        ProcessRanking {
+            timestamp: Utc::now().timestamp_millis() as u64,
+            rank_by: key,
+            processes,
+        }
+    }
+
+    /// 全系统磁盘 IO 速率最高的进程，不受进程选择条件影响，只包含有 IO 的进程
+    pub fn collect_top_io(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let filter = std::mem::take(&mut self.filter);
+        // io_rate 在没有权限读取 IO 时为 0，一并排除
+        let ranking = self.rank_matching(RankKey::Io, TOP_IO_PROCESSES, |process| process.io_rate() > 0.0);
+        self.filter = filter;
+        Ok(serde_json::to_string_pretty(&ranking)?)
+    }
+
+    /// 满足选择条件的进程中线程数最多的进程，与排名相同，只有显式指定最小线程数时才按线程数过滤
+    pub fn check_max_threads(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut max_thread_count = 0;
+        let mut max_process_infos = Vec::new();
+
+        let entries = self.list_processes();
+        for process in entries.into_iter().filter(|process| self.filter.accepts_for_ranking(process)) {
+            let thread_count = process.thread_count;
+            if thread_count > max_thread_count {
+                max_thread_count = thread_count;
+                max_process_infos.clear();
+                max_process_infos.push((process.pid, process.name, thread_count));
+            } else if thread_count == max_thread_count && max_thread_count > 0 {
+                max_process_infos.push((process.pid, process.name, thread_count));
+            }
+        }
+
+        if !max_process_infos.is_empty() {
+            let results: Vec<_> = max_process_infos.into_iter().map(|(pid, name, count)| {
+                serde_json::json!({
+                    "pid": pid,
+                    "name": name,
+                    "thread_count": count
+                })
+            }).collect();
+            Ok(serde_json::to_string_pretty(&results)?)
+        } else {
+            Ok("[]".to_string())
+        }
+    }
 }
 
-pub fn check_max_threads_process() -> Result<String, Box<dyn std::error::Error>> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    
-    let mut max_thread_count = 0;
-    let mut max_process_infos = Vec::new();
-
-    for (pid, process) in sys.processes() {
-        let thread_count = get_thread_count(pid.as_u32());
-        if thread_count > max_thread_count {
-            max_thread_count = thread_count;
-            max_process_infos.clear();
-            max_process_infos.push((pid.as_u32(), process.name().to_string_lossy().to_string(), thread_count));
-        } else if thread_count == max_thread_count && max_thread_count > 0 {
-            max_process_infos.push((pid.as_u32(), process.name().to_string_lossy().to_string(), thread_count));
-        }
-    }
-
-    if !max_process_infos.is_empty() {
-        let results: Vec<_> = max_process_infos.into_iter().map(|(pid, name, count)| {
-            serde_json::json!({
-                "pid": pid,
-                "name": name,
-                "thread_count": count
+// 累计的主动、被动上下文切换次数（status）与次、主缺页次数（stat）
+#[cfg(target_os = "linux")]
+fn activity_counters(stat: &procfs::process::Stat, switches: Option<[u64; 2]>) -> Option<[u64; 4]> {
+    let [voluntary, nonvoluntary] = switches?;
+    Some([voluntary, nonvoluntary, stat.minflt, stat.majflt])
+}
+
+#[cfg(target_os = "linux")]
+fn context_switches(status: &procfs::process::Status) -> Option<[u64; 2]> {
+    Some([status.voluntary_ctxt_switches?, status.nonvoluntary_ctxt_switches?])
+}
+
+// 进程的累计上下文切换次数。stat 中的缺页次数已包含所有线程，
+// 而 status 中的上下文切换只是主线程的，多线程进程需要累加各线程
+#[cfg(target_os = "linux")]
+fn process_context_switches(proc: &procfs::process::Process, stat: &procfs::process::Stat, status: Option<&procfs::process::Status>) -> Option<[u64; 2]> {
+    if stat.num_threads <= 1 {
+        return context_switches(status?);
+    }
+    let mut total = [0, 0];
+    for task in proc.tasks().ok()?.flatten() {
+        let Some([voluntary, nonvoluntary]) = task.status().ok().as_ref().and_then(context_switches) else { continue };
+        total[0] += voluntary;
+        total[1] += nonvoluntary;
+    }
+    Some(total)
+}
+
+// 读取线程的 wchan、syscall 与内核栈，没有权限或内核不支持的项为空
+#[cfg(target_os = "linux")]
+fn read_kernel_wait(pid: u32, tid: u32) -> KernelWaitInfo {
+    let source = source::current();
+    let read = |name: &str| {
+        source.read_proc(format!("{}/task/{}/{}", pid, tid, name))
+            .ok()
+            .map(|content| content.trim().to_string())
+    };
+
+    KernelWaitInfo {
+        // 未阻塞时 wchan 为 0
+        wchan: read("wchan").filter(|wchan| !wchan.is_empty() && wchan != "0"),
+        syscall: read("syscall").filter(|syscall| !syscall.is_empty()),
+        // 每行格式: [<0>] do_select+0x6e0/0x7c0
+        stack: read("stack")
+            .map(|stack| {
+                stack.lines()
+                    .map(|line| line.split_once("] ").map(|(_, frame)| frame).unwrap_or(line).to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// 导出指定进程所有线程的状态、wchan、syscall 与内核栈，用于排查卡死的进程
+pub fn dump_thread_stacks(pid: u32) -> Result<String, Box<dyn std::error::Error>> {
+    #[cfg(target_os = "linux")]
+    {
+        let proc = open_process(pid)?;
+        let stat = proc.stat()?;
+
+        let threads: Vec<ThreadStack> = proc.tasks()?
+            .flatten()
+            .filter_map(|task| {
+                let task_stat = task.stat().ok()?;
+                Some(ThreadStack {
+                    thread_id: task_stat.pid as u32,
+                    command: task_stat.comm,
+                    status: process_status_name(task_stat.state),
+                    kernel_wait: read_kernel_wait(pid, task_stat.pid as u32),
+                })
             })
-        }).collect();
-        Ok(serde_json::to_string_pretty(&results)?)
-    } else {
-        Ok("[]".to_string())
+            .collect();
+
+        let dump = serde_json::json!({
+            "pid": pid,
+            "name": stat.comm,
+            "timestamp": Utc::now().timestamp_millis() as u64,
+            "threads": threads,
+        });
+        Ok(serde_json::to_string_pretty(&dump)?)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        Err("thread stacks are only available on Linux".into())
+    }
+}
+
+// kthreadd（pid 2）及其创建的内核线程
+fn is_kernel_thread(process: &ProcessEntry) -> bool {
+    cfg!(target_os = "linux") && (process.pid == 2 || process.ppid == 2)
+}
+
+// 各用户的线程总数，RLIMIT_NPROC 按用户计算
+fn user_task_counts(entries: &[ProcessEntry]) -> HashMap<String, u64> {
+    let mut user_tasks = HashMap::new();
+    for process in entries {
+        *user_tasks.entry(process.user_name.clone()).or_default() += process.thread_count as u64;
+    }
+    user_tasks
+}
+
+// 沿父进程链向上查找是否有选中的祖先进程
+fn has_selected_ancestor(entries: &[ProcessEntry], index_of: &HashMap<u32, usize>, selected: &HashSet<u32>, index: usize) -> bool {
+    let mut visited = HashSet::from([entries[index].pid]);
+    let mut ppid = entries[index].ppid;
+    while let Some(&parent) = index_of.get(&ppid) {
+        if !visited.insert(ppid) {
+            return false;
+        }
+        if selected.contains(&ppid) {
+            return true;
+        }
+        ppid = entries[parent].ppid;
+    }
+    false
+}
+
+// 递归构建以 entries[index] 为根的子树，子进程按 pid 排序
+fn build_tree_node(entries: &[ProcessEntry], children: &HashMap<u32, Vec<usize>>, index: usize, total_memory: f64) -> ProcessTreeNode {
+    let process = &entries[index];
+    let memory_usage = |bytes: u64| {
+        if total_memory > 0.0 { util::round1(bytes as f64 / total_memory * 100.0) } else { 0.0 }
+    };
+
+    let mut child_indices = children.get(&process.pid).cloned().unwrap_or_default();
+    child_indices.sort_by_key(|&child| entries[child].pid);
+    let child_nodes: Vec<ProcessTreeNode> = child_indices.into_iter()
+        .map(|child| build_tree_node(entries, children, child, total_memory))
+        .collect();
+
+    let mut subtree = SubtreeTotals {
+        process_count: 1,
+        cpu_usage: process.cpu_usage,
+        memory_bytes: process.memory,
+        memory_usage: 0.0,
+        thread_count: process.thread_count,
+    };
+    for child in &child_nodes {
+        subtree.process_count += child.subtree.process_count;
+        subtree.cpu_usage += child.subtree.cpu_usage;
+        subtree.memory_bytes += child.subtree.memory_bytes;
+        subtree.thread_count += child.subtree.thread_count;
+    }
+    subtree.cpu_usage = util::round1(subtree.cpu_usage);
+    subtree.memory_usage = memory_usage(subtree.memory_bytes);
+
+    ProcessTreeNode {
+        pid: process.pid,
+        ppid: process.ppid,
+        name: process.name.clone(),
+        user_name: process.user_name.clone(),
+        status: process.status.clone(),
+        cpu_usage: process.cpu_usage,
+        memory_bytes: process.memory,
+        memory_usage: memory_usage(process.memory),
+        thread_count: process.thread_count,
+        subtree,
+        children: child_nodes,
+    }
+}
+
+/// 单次采集进程信息（用于 process 子命令）
+pub fn collect_processes(config: ProcessConfig) -> Result<String, Box<dyn std::error::Error>> {
+    ProcessCollector::new(config).collect()
+}
+
+/// 单次构建进程树（用于 process --tree）
+pub fn collect_process_tree(config: ProcessConfig) -> Result<String, Box<dyn std::error::Error>> {
+    ProcessCollector::new(config).collect_tree()
+}
+
+/// 单次检查线程数是否接近各级限制（用于 process --thread-limits）
+pub fn check_thread_limits(config: ProcessConfig) -> Result<String, Box<dyn std::error::Error>> {
+    ProcessCollector::new(config).check_thread_limits()
+}
+
+/// 单次查找线程数最多的进程（用于 process --check）
+pub fn check_max_threads_process(config: ProcessConfig) -> Result<String, Box<dyn std::error::Error>> {
+    ProcessCollector::new(config).check_max_threads()
+}
+
+/// 进程排名（用于 process --check），未指定排名依据且输出 JSON 时保持原有的线程数最多进程输出
+pub fn check_processes(config: ProcessConfig, ranking: RankingConfig) -> Result<String, Box<dyn std::error::Error>> {
+    if ranking.rank_by.is_none() && ranking.format == OutputFormat::Json {
+        return check_max_threads_process(config);
+    }
+
+    let key = ranking.rank_by.unwrap_or(RankKey::Threads);
+    let result = ProcessCollector::new(config).rank(key, ranking.rank_top);
+    match ranking.format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(&result)?),
+        OutputFormat::Table => Ok(result.to_table()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::fixture::Fixture;
+
+    // 生成 /proc/<pid>/stat 一行，只填写采集用到的字段
+    fn stat_line(pid: u32, comm: &str, utime: u64, num_threads: u32, rss_pages: u64) -> String {
+        format!(
+            "{pid} ({comm}) S 1 {pid} {pid} 0 -1 4194304 0 0 0 0 {utime} 0 0 0 20 0 {num_threads} 0 100 \
+             4096000 {rss_pages} 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n"
+        )
+    }
+
+    // 写入一个双线程进程，线程 CPU tick 平分进程的 tick
+    fn write_process(fixture: &Fixture, pid: u32, comm: &str, utime: u64, rss_pages: u64) {
+        let tids = [pid, pid + 1];
+        let mut files = vec![
+            (format!("proc/{pid}/stat"), stat_line(pid, comm, utime, 2, rss_pages)),
+            (format!("proc/{pid}/status"), format!("Name:\t{comm}\n")),
+        ];
+        for tid in tids {
+            files.push((format!("proc/{pid}/task/{tid}/stat"), stat_line(tid, comm, utime / 2, 2, rss_pages)));
+        }
+        let files: Vec<(&str, &str)> = files.iter().map(|(path, content)| (path.as_str(), content.as_str())).collect();
+        fixture.write(&files);
+    }
+
+    // 写入 /proc/<pid>/io
+    fn write_io(fixture: &Fixture, pid: u32, read_bytes: u64) {
+        let io = format!(
+            "rchar: {read_bytes}\nwchar: 0\nsyscr: 10\nsyscw: 0\nread_bytes: {read_bytes}\nwrite_bytes: 0\ncancelled_write_bytes: 0\n"
+        );
+        fixture.write(&[(&format!("proc/{pid}/io"), &io)]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn io_ranking_skips_unknown_and_idle_processes() {
+        let fixture = Fixture::new();
+        write_process(&fixture, 4400, "io-busy", 100, 1000);
+        write_process(&fixture, 4500, "io-idle", 100, 1000);
+        write_process(&fixture, 4600, "io-denied", 100, 1000);
+        write_io(&fixture, 4400, 4096);
+        write_io(&fixture, 4500, 4096);
+
+        let mut collector = ProcessCollector::new(ProcessConfig {
+            filter: ProcessFilter {
+                names: vec![Regex::new("^io-").unwrap()],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        collector.prime();
+        write_io(&fixture, 4400, 8192);
+
+        let ranking = collector.rank(RankKey::Io, 0);
+        let pids: Vec<u32> = ranking.processes.iter().map(|process| process.pid).collect();
+        assert_eq!(pids, [4400, 4500, 4600]);
+        assert!(ranking.processes[2].io.is_none());
+
+        write_io(&fixture, 4400, 16384);
+        let top: ProcessRanking = serde_json::from_str(&collector.collect_top_io().unwrap()).unwrap();
+        let pids: Vec<u32> = top.processes.iter().map(|process| process.pid).collect();
+        assert_eq!(pids, [4400]);
+    }
+
+    #[test]
+    fn format_memory_keeps_v1_truncation() {
+        assert_eq!(format_memory(46_387, SchemaVersion::V1), "45M");
+        assert_eq!(format_memory(1_992_294, SchemaVersion::V1), "1G");
+        assert_eq!(format_memory(512, SchemaVersion::V1), "512K");
+        assert_eq!(format_memory(46_387, SchemaVersion::V2), "45.3M");
+        assert_eq!(format_memory(1_992_294, SchemaVersion::V2), "1.9G");
+        assert_eq!(format_memory(2 * 1024 * 1024, SchemaVersion::V2), "2G");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn newly_selected_threads_have_no_cpu_until_sampled() {
+        let fixture = Fixture::new();
+        write_process(&fixture, 4700, "tick-big", 100, 2000);
+        write_process(&fixture, 4800, "tick-small", 100, 1000);
+
+        let mut collector = ProcessCollector::new(ProcessConfig {
+            filter: ProcessFilter {
+                top: Some(1),
+                top_by: ProcessSortKey::Memory,
+                ..Default::default()
+            },
+            schema_version: SchemaVersion::V2,
+            ..Default::default()
+        });
+        let first = collector.collect_tick();
+        assert_eq!(first.process[0].pid, 4700);
+
+        // small 进入 top 集合，但上一轮只采样了 big 的线程
+        write_process(&fixture, 4800, "tick-small", 300, 3000);
+        let second = collector.collect_tick();
+        assert_eq!(second.process[0].pid, 4800);
+        assert!(second.process[0].threads.iter().all(|thread| thread.cpu_usage_percent.is_none()));
+
+        write_process(&fixture, 4800, "tick-small", 500, 3000);
+        let third = collector.collect_tick();
+        assert!(third.process[0].threads.iter().all(|thread| thread.cpu_usage_percent.unwrap() > 0.0));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn threads_without_baseline_sort_by_cumulative_cpu() {
+        let fixture = Fixture::new();
+        write_process(&fixture, 4900, "sort-big", 100, 2000);
+        write_process(&fixture, 5000, "sort-small", 100, 1000);
+
+        let mut collector = ProcessCollector::new(ProcessConfig {
+            filter: ProcessFilter {
+                top: Some(1),
+                top_by: ProcessSortKey::Memory,
+                ..Default::default()
+            },
+            schema_version: SchemaVersion::V2,
+            threads: ThreadSelection { thread_top: 1, ..Default::default() },
+            ..Default::default()
+        });
+        assert_eq!(collector.collect_tick().process[0].pid, 4900);
+
+        // small 刚被选中，线程还没有 CPU 基准，5001 的累计 CPU 时间更长
+        write_process(&fixture, 5000, "sort-small", 100, 3000);
+        fixture.write(&[("proc/5000/task/5001/stat", &stat_line(5001, "sort-small", 90, 2, 3000))]);
+        let tick = collector.collect_tick();
+        assert_eq!(tick.process[0].pid, 5000);
+        assert_eq!(tick.process[0].threads.len(), 1);
+        assert_eq!(tick.process[0].threads[0].thread_id, 5001);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn prime_samples_threads_outside_initial_top() {
+        let fixture = Fixture::new();
+        // 首次采集时 big 内存更大，正式采集时 small 超过 big
+        write_process(&fixture, 4100, "prime-big", 100, 2000);
+        write_process(&fixture, 4200, "prime-small", 100, 1000);
+
+        let mut collector = ProcessCollector::new(ProcessConfig {
+            filter: ProcessFilter {
+                names: vec![Regex::new("^prime-").unwrap()],
+                top: Some(1),
+                top_by: ProcessSortKey::Memory,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        collector.prime();
+
+        write_process(&fixture, 4100, "prime-big", 100, 1000);
+        write_process(&fixture, 4200, "prime-small", 300, 3000);
+        let processes: Vec<ProcessData> = serde_json::from_str(&collector.collect().unwrap()).unwrap();
+
+        assert_eq!(processes.len(), 1);
+        assert_eq!(processes[0].pid, 4200);
+        assert_eq!(processes[0].threads.len(), 2);
+        for thread in &processes[0].threads {
+            assert!(thread.cpu_usage.parse::<f64>().unwrap() > 0.0, "thread {} has no baseline", thread.thread_id);
+        }
     }
 }
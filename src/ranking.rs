@@ -0,0 +1,120 @@
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::process::{format_memory, ProcessIo, SchemaVersion};
+
+/// 进程排名依据，均为降序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum RankKey {
+    /// 线程数
+    Threads,
+    /// CPU 使用率
+    Cpu,
+    /// 常驻内存
+    Rss,
+    /// 打开的文件描述符数
+    Fds,
+    /// 磁盘读写速率
+    Io,
+    /// 上下文切换速率
+    ContextSwitches,
+}
+
+/// 排名的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    /// 便于阅读的对齐表格
+    Table,
+}
+
+/// process --check 的排名参数
+#[derive(Debug, Clone, Args)]
+pub struct RankingConfig {
+    /// 按此依据输出排名前 N 的进程，不指定时只输出线程数最多的进程
+    #[arg(long, value_enum, requires = "check")]
+    pub rank_by: Option<RankKey>,
+    /// 排名输出的进程数，0 表示不限制
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "check")]
+    pub rank_top: usize,
+    /// 排名的输出格式，table 未指定 --rank-by 时按线程数排名
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json, requires = "check")]
+    pub format: OutputFormat,
+}
+
+/// 排名中的单个进程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedProcess {
+    pub rank: usize,
+    pub pid: u32,
+    pub name: String,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    #[serde(rename = "threadCount")]
+    pub thread_count: u32,
+    #[serde(rename = "cpuUsage")]
+    pub cpu_usage: f64,
+    /// 常驻内存（字节）
+    #[serde(rename = "memoryBytes")]
+    pub memory_bytes: u64,
+    /// 打开的文件描述符数，没有权限时为空
+    #[serde(rename = "fdCount", default, skip_serializing_if = "Option::is_none")]
+    pub fd_count: Option<u64>,
+    /// 磁盘读写速率（字节/秒），没有权限读取 IO 时为 0，且 io 为空
+    #[serde(rename = "ioRate")]
+    pub io_rate: f64,
+    /// 磁盘 IO 速率明细
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io: Option<ProcessIo>,
+    /// 上下文切换速率（次/秒）
+    #[serde(rename = "contextSwitchRate")]
+    pub context_switch_rate: f64,
+}
+
+/// 进程排名结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessRanking {
+    pub timestamp: u64,
+    #[serde(rename = "rankBy")]
+    pub rank_by: RankKey,
+    pub processes: Vec<RankedProcess>,
+}
+
+impl ProcessRanking {
+    /// 以对齐的表格输出，排名依据所在的列标题带 *，内存与 IO 保留一位小数
+    pub fn to_table(&self) -> String {
+        let title = |name: &str, key: RankKey| {
+            if key == self.rank_by { format!("{}*", name) } else { name.to_string() }
+        };
+        let mut lines = vec![format!(
+            "{:>4} {:>8} {:<12} {:>8} {:>7} {:>8} {:>6} {:>10} {:>9}  NAME",
+            "RANK", "PID", "USER",
+            title("THREADS", RankKey::Threads),
+            title("CPU%", RankKey::Cpu),
+            title("RSS", RankKey::Rss),
+            title("FDS", RankKey::Fds),
+            title("IO/s", RankKey::Io),
+            title("CSW/s", RankKey::ContextSwitches),
+        )];
+
+        for process in &self.processes {
+            lines.push(format!(
+                "{:>4} {:>8} {:<12} {:>8} {:>7.1} {:>8} {:>6} {:>10} {:>9.1}  {}",
+                process.rank,
+                process.pid,
+                process.user_name,
+                process.thread_count,
+                process.cpu_usage,
+                format_memory(process.memory_bytes / 1024, SchemaVersion::V2),
+                process.fd_count.map(|count| count.to_string()).unwrap_or_else(|| "-".to_string()),
+                process.io.as_ref()
+                    .map(|_| format_memory(process.io_rate as u64 / 1024, SchemaVersion::V2))
+                    .unwrap_or_else(|| "-".to_string()),
+                process.context_switch_rate,
+                process.name,
+            ));
+        }
+        lines.join("\n")
+    }
+}
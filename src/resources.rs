@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+/// 进程打开的文件描述符按类型统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FdStats {
+    pub total: u64,
+    pub sockets: u64,
+    pub pipes: u64,
+    /// 普通文件、设备及 memfd
+    pub files: u64,
+    /// eventfd、epoll、timerfd 等没有 inode 的描述符
+    #[serde(rename = "anonInodes")]
+    pub anon_inodes: u64,
+    /// 其他类型及无法读取链接的描述符
+    pub other: u64,
+}
+
+/// 单项资源限制及当前用量，限制为 unlimited 时对应字段为空
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceLimit {
+    pub current: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hard: Option<u64>,
+    /// 相对软限制的使用率 (%)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<f64>,
+}
+
+/// 进程的文件描述符与资源限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessResources {
+    pub fds: FdStats,
+    /// RLIMIT_NOFILE，当前值为打开的描述符数
+    #[serde(rename = "openFiles")]
+    pub open_files: ResourceLimit,
+    /// RLIMIT_NPROC，按内核的计算方式，当前值为同一用户的线程总数
+    pub processes: ResourceLimit,
+    /// RLIMIT_AS，当前值为虚拟内存大小（字节）
+    #[serde(rename = "addressSpace")]
+    pub address_space: ResourceLimit,
+}
+
+/// 读取进程的文件描述符统计与资源限制，没有权限时返回 None
+///
+/// # Arguments
+/// * `user_tasks` - 进程所属用户的线程总数，用于计算 RLIMIT_NPROC 使用率
+#[cfg(target_os = "linux")]
+pub fn read_process_resources(pid: u32, user_tasks: u64) -> Option<ProcessResources> {
+    use procfs::process::FDTarget;
+
+    let proc = procfs::process::Process::new_with_root(crate::source::current().proc_path(pid.to_string())).ok()?;
+    let limits = proc.limits().ok()?;
+
+    let mut fds = FdStats::default();
+    for fd in proc.fd().ok()?.flatten() {
+        fds.total += 1;
+        match fd.target {
+            FDTarget::Socket(_) => fds.sockets += 1,
+            FDTarget::Pipe(_) => fds.pipes += 1,
+            FDTarget::Path(_) | FDTarget::MemFD(_) => fds.files += 1,
+            FDTarget::AnonInode(_) => fds.anon_inodes += 1,
+            FDTarget::Net(_) | FDTarget::Other(..) => fds.other += 1,
+        }
+    }
+    // procfs 会跳过读不到链接（如 EACCES）的描述符，总数以目录项为准，差值计入 other，
+    // 避免低估 RLIMIT_NOFILE 使用率
+    let listed = count_fds(pid)?;
+    if listed > fds.total {
+        fds.other += listed - fds.total;
+        fds.total = listed;
+    }
+
+    let virtual_memory = proc.statm()
+        .map(|statm| statm.size * procfs::page_size())
+        .unwrap_or(0);
+
+    Some(ProcessResources {
+        open_files: resource_limit(fds.total, &limits.max_open_files),
+        processes: resource_limit(user_tasks, &limits.max_processes),
+        address_space: resource_limit(virtual_memory, &limits.max_address_space),
+        fds,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_process_resources(pid: u32, user_tasks: u64) -> Option<ProcessResources> {
+    let _ = (pid, user_tasks);
+    None
+}
+
+/// 进程打开的文件描述符数，只统计数量不区分类型，没有权限时返回 None
+#[cfg(target_os = "linux")]
+pub fn count_fds(pid: u32) -> Option<u64> {
+    let dir = crate::source::current().proc_path(format!("{}/fd", pid));
+    Some(std::fs::read_dir(dir).ok()?.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn count_fds(pid: u32) -> Option<u64> {
+    let _ = pid;
+    None
+}
+
+/// 进程的 RLIMIT_NPROC 软限制，unlimited 或读取失败时返回 None
+#[cfg(target_os = "linux")]
+pub fn read_nproc_limit(pid: u32) -> Option<u64> {
+    let proc = procfs::process::Process::new_with_root(crate::source::current().proc_path(pid.to_string())).ok()?;
+    match proc.limits().ok()?.max_processes.soft_limit {
+        procfs::process::LimitValue::Value(value) => Some(value),
+        procfs::process::LimitValue::Unlimited => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_nproc_limit(pid: u32) -> Option<u64> {
+    let _ = pid;
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn resource_limit(current: u64, limit: &procfs::process::Limit) -> ResourceLimit {
+    use procfs::process::LimitValue;
+
+    let value = |limit: &LimitValue| match limit {
+        LimitValue::Value(value) => Some(*value),
+        LimitValue::Unlimited => None,
+    };
+    let soft = value(&limit.soft_limit);
+    ResourceLimit {
+        current,
+        soft,
+        hard: value(&limit.hard_limit),
+        usage: soft.filter(|s| *s > 0).map(|s| crate::util::round1(current as f64 / s as f64 * 100.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unreadable_fds_count_towards_open_files() {
+        let fixture = crate::source::fixture::Fixture::new();
+        let limits = "Limit                     Soft Limit           Hard Limit           Units     \n\
+            Max cpu time              unlimited            unlimited            seconds   \n\
+            Max file size             unlimited            unlimited            bytes     \n\
+            Max data size             unlimited            unlimited            bytes     \n\
+            Max stack size            8388608              unlimited            bytes     \n\
+            Max core file size        0                    unlimited            bytes     \n\
+            Max resident set          unlimited            unlimited            bytes     \n\
+            Max processes             1000                 1000                 processes \n\
+            Max open files            4                    4                    files     \n\
+            Max locked memory         8388608              8388608              bytes     \n\
+            Max address space         unlimited            unlimited            bytes     \n\
+            Max file locks            unlimited            unlimited            locks     \n\
+            Max pending signals       1000                 1000                 signals   \n\
+            Max msgqueue size         819200               819200               bytes     \n\
+            Max nice priority         0                    0                    \n\
+            Max realtime priority     0                    0                    \n\
+            Max realtime timeout      unlimited            unlimited            us        \n";
+        fixture.write(&[("proc/4300/limits", limits), ("proc/4300/fd/1", "")]);
+        // fd 1 不是链接，读取目标失败，与没有权限读取链接的情况相同
+        let _ = std::fs::remove_file(fixture.root().join("proc/4300/fd/0"));
+        std::os::unix::fs::symlink("/dev/null", fixture.root().join("proc/4300/fd/0")).unwrap();
+
+        let resources = read_process_resources(4300, 1).unwrap();
+        assert_eq!(resources.fds.total, 2);
+        assert_eq!(resources.fds.files, 1);
+        assert_eq!(resources.fds.other, 1);
+        assert_eq!(resources.open_files.current, 2);
+        assert_eq!(resources.open_files.usage, Some(50.0));
+    }
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// hwmon 温度传感器（摄氏度）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemperatureSensor {
+    /// 芯片名称，例如 coretemp、k10temp、nvme
+    pub chip: String,
+    pub label: String,
+    pub temperature: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub critical: Option<f64>,
+}
+
+/// hwmon 风扇转速
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanSensor {
+    pub chip: String,
+    pub label: String,
+    pub rpm: u64,
+}
+
+/// 温控区的触发点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripPoint {
+    /// 触发类型：active、passive、hot、critical
+    #[serde(rename = "tripType")]
+    pub trip_type: String,
+    pub temperature: f64,
+}
+
+/// /sys/class/thermal 下的温控区
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThermalZone {
+    pub name: String,
+    #[serde(rename = "zoneType")]
+    pub zone_type: String,
+    pub temperature: f64,
+    #[serde(default)]
+    pub trips: Vec<TripPoint>,
+}
+
+/// 硬件传感器数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SensorData {
+    #[serde(default)]
+    pub temperatures: Vec<TemperatureSensor>,
+    #[serde(default)]
+    pub fans: Vec<FanSensor>,
+    #[serde(rename = "thermalZones", default)]
+    pub thermal_zones: Vec<ThermalZone>,
+}
+
+impl SensorData {
+    pub fn is_empty(&self) -> bool {
+        self.temperatures.is_empty() && self.fans.is_empty() && self.thermal_zones.is_empty()
+    }
+}
+
+/// 从 sysfs 采集 hwmon 与温控区数据
+///
+/// # Arguments
+/// * `sys_root` - sysfs 根目录，正常为 /sys，也可以指向采集好的目录快照
+pub fn collect_sensors(sys_root: &Path) -> SensorData {
+    let mut data = SensorData::default();
+
+    for hwmon in sorted_entries(&sys_root.join("class/hwmon"), "hwmon") {
+        let chip = read_trimmed(&hwmon.join("name")).unwrap_or_else(|| file_name(&hwmon));
+        collect_hwmon(&hwmon, &chip, &mut data);
+    }
+
+    for zone in sorted_entries(&sys_root.join("class/thermal"), "thermal_zone") {
+        if let Some(thermal_zone) = read_thermal_zone(&zone) {
+            data.thermal_zones.push(thermal_zone);
+        }
+    }
+
+    data
+}
+
+fn collect_hwmon(dir: &Path, chip: &str, data: &mut SensorData) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let mut inputs: Vec<String> = entries
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with("_input"))
+        .collect();
+    inputs.sort_by_key(|name| natural_key(name));
+
+    for input in inputs {
+        // 例如 temp1_input → temp1
+        let sensor = input.trim_end_matches("_input");
+        let label = read_trimmed(&dir.join(format!("{}_label", sensor)))
+            .unwrap_or_else(|| sensor.to_string());
+        let Some(value) = read_number(&dir.join(&input)) else { continue };
+
+        if sensor.starts_with("temp") {
+            data.temperatures.push(TemperatureSensor {
+                chip: chip.to_string(),
+                label,
+                temperature: millidegrees(value),
+                max: read_number(&dir.join(format!("{}_max", sensor))).map(millidegrees),
+                critical: read_number(&dir.join(format!("{}_crit", sensor))).map(millidegrees),
+            });
+        } else if sensor.starts_with("fan") {
+            data.fans.push(FanSensor {
+                chip: chip.to_string(),
+                label,
+                rpm: value.max(0) as u64,
+            });
+        }
+    }
+}
+
+fn read_thermal_zone(dir: &Path) -> Option<ThermalZone> {
+    let temperature = millidegrees(read_number(&dir.join("temp"))?);
+    let mut trips = Vec::new();
+    // 触发点编号从 0 开始连续排列
+    for index in 0.. {
+        let Some(trip_type) = read_trimmed(&dir.join(format!("trip_point_{}_type", index))) else { break };
+        let Some(trip_temp) = read_number(&dir.join(format!("trip_point_{}_temp", index))) else { break };
+        trips.push(TripPoint {
+            trip_type,
+            temperature: millidegrees(trip_temp),
+        });
+    }
+
+    Some(ThermalZone {
+        name: file_name(dir),
+        zone_type: read_trimmed(&dir.join("type")).unwrap_or_default(),
+        temperature,
+        trips,
+    })
+}
+
+// 列出目录下以 prefix 开头的条目，按编号排序
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| file_name(p).starts_with(prefix))
+        .collect();
+    paths.sort_by_key(|p| natural_key(&file_name(p)));
+    paths
+}
+
+// 按名称中的数字排序，使 temp10 排在 temp2 之后
+fn natural_key(name: &str) -> (String, u64) {
+    let prefix: String = name.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let number = name[prefix.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0);
+    (prefix, number)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_number(path: &Path) -> Option<i64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+fn millidegrees(value: i64) -> f64 {
+    value as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::fixture::Fixture;
+
+    #[test]
+    fn parses_hwmon_and_thermal_zones() {
+        let fixture = Fixture::new();
+        fixture.write(&[
+            ("sys/class/hwmon/hwmon0/name", "coretemp\n"),
+            ("sys/class/hwmon/hwmon0/temp1_input", "45000\n"),
+            ("sys/class/hwmon/hwmon0/temp1_label", "Package id 0\n"),
+            ("sys/class/hwmon/hwmon0/temp1_max", "80000\n"),
+            ("sys/class/hwmon/hwmon0/temp1_crit", "100000\n"),
+            ("sys/class/hwmon/hwmon0/fan1_input", "1200\n"),
+            ("sys/class/thermal/thermal_zone0/type", "x86_pkg_temp\n"),
+            ("sys/class/thermal/thermal_zone0/temp", "47500\n"),
+            ("sys/class/thermal/thermal_zone0/trip_point_0_type", "passive\n"),
+            ("sys/class/thermal/thermal_zone0/trip_point_0_temp", "95000\n"),
+        ]);
+
+        let data = collect_sensors(&fixture.root().join("sys"));
+
+        assert_eq!(data.temperatures.len(), 1);
+        let temp = &data.temperatures[0];
+        assert_eq!(temp.chip, "coretemp");
+        assert_eq!(temp.label, "Package id 0");
+        assert_eq!(temp.temperature, 45.0);
+        assert_eq!(temp.max, Some(80.0));
+        assert_eq!(temp.critical, Some(100.0));
+
+        assert_eq!(data.fans.len(), 1);
+        assert_eq!(data.fans[0].label, "fan1");
+        assert_eq!(data.fans[0].rpm, 1200);
+
+        assert_eq!(data.thermal_zones.len(), 1);
+        let zone = &data.thermal_zones[0];
+        assert_eq!(zone.name, "thermal_zone0");
+        assert_eq!(zone.zone_type, "x86_pkg_temp");
+        assert_eq!(zone.temperature, 47.5);
+        assert_eq!(zone.trips.len(), 1);
+        assert_eq!(zone.trips[0].trip_type, "passive");
+        assert_eq!(zone.trips[0].temperature, 95.0);
+    }
+
+    #[test]
+    fn skips_missing_and_unreadable_files() {
+        let fixture = Fixture::new();
+        fixture.write(&[
+            // 没有 name 时芯片名取目录名，没有 label 时取传感器名
+            ("sys/class/hwmon/hwmon0/temp1_input", "30000\n"),
+            ("sys/class/hwmon/hwmon0/temp2_input", "not a number\n"),
+            ("sys/class/hwmon/hwmon0/temp2_label", "Broken\n"),
+            // 没有 temp 的温控区不输出
+            ("sys/class/thermal/thermal_zone0/type", "acpitz\n"),
+        ]);
+        // 无法读取的输入文件（目录读取会失败，root 下也同样有效）
+        std::fs::create_dir_all(fixture.root().join("sys/class/hwmon/hwmon0/temp3_input")).unwrap();
+
+        let data = collect_sensors(&fixture.root().join("sys"));
+
+        assert_eq!(data.temperatures.len(), 1);
+        assert_eq!(data.temperatures[0].chip, "hwmon0");
+        assert_eq!(data.temperatures[0].label, "temp1");
+        assert_eq!(data.temperatures[0].temperature, 30.0);
+        assert_eq!(data.temperatures[0].max, None);
+        assert!(data.fans.is_empty());
+        assert!(data.thermal_zones.is_empty());
+
+        // 整个 sysfs 目录不存在
+        let empty = collect_sensors(&fixture.root().join("missing"));
+        assert!(empty.is_empty());
+    }
+}
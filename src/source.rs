@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const LIVE_PROC_ROOT: &str = "/proc";
+const LIVE_SYS_ROOT: &str = "/sys";
+
+/// 采集数据源：procfs 与 sysfs 的根目录
+///
+/// 默认读取本机的 /proc 与 /sys，也可以指向 capture 子命令录制的目录快照，
+/// 用于针对采集好的机器数据回归测试解析逻辑
+#[derive(Debug, Clone)]
+pub struct Source {
+    proc_root: PathBuf,
+    sys_root: PathBuf,
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source {
+            proc_root: PathBuf::from(LIVE_PROC_ROOT),
+            sys_root: PathBuf::from(LIVE_SYS_ROOT),
+        }
+    }
+}
+
+impl Source {
+    pub fn new(proc_root: Option<PathBuf>, sys_root: Option<PathBuf>) -> Self {
+        Source {
+            proc_root: proc_root.unwrap_or_else(|| PathBuf::from(LIVE_PROC_ROOT)),
+            sys_root: sys_root.unwrap_or_else(|| PathBuf::from(LIVE_SYS_ROOT)),
+        }
+    }
+
+    pub fn proc_root(&self) -> &Path {
+        &self.proc_root
+    }
+
+    pub fn sys_root(&self) -> &Path {
+        &self.sys_root
+    }
+
+    /// 拼接 procfs 下的路径，例如 proc_path("stat")
+    pub fn proc_path(&self, relative: impl AsRef<Path>) -> PathBuf {
+        self.proc_root.join(relative)
+    }
+
+    /// 拼接 sysfs 下的路径，例如 sys_path("class/hwmon")
+    pub fn sys_path(&self, relative: impl AsRef<Path>) -> PathBuf {
+        self.sys_root.join(relative)
+    }
+
+    pub fn read_proc(&self, relative: impl AsRef<Path>) -> std::io::Result<String> {
+        std::fs::read_to_string(self.proc_path(relative))
+    }
+
+    /// 系统启动后经过的秒数，取自 uptime
+    pub fn uptime(&self) -> Option<f64> {
+        self.read_proc("uptime").ok()?.split_whitespace().next()?.parse().ok()
+    }
+
+    /// 是否读取本机实时数据，快照中无法获得的数据（statvfs、dmesg 命令等）据此跳过
+    pub fn is_live(&self) -> bool {
+        self.proc_root == Path::new(LIVE_PROC_ROOT) && self.sys_root == Path::new(LIVE_SYS_ROOT)
+    }
+}
+
+static SOURCE: OnceLock<Source> = OnceLock::new();
+
+/// 设置全局数据源，只在启动时调用一次
+pub fn init(source: Source) {
+    let _ = SOURCE.set(source);
+}
+
+/// 当前使用的数据源
+pub fn current() -> &'static Source {
+    #[cfg(test)]
+    if let Some(source) = fixture::OVERRIDE.with(std::cell::Cell::get) {
+        return source;
+    }
+    SOURCE.get_or_init(Source::default)
+}
+
+/// 测试用的目录快照
+///
+/// 测试不设置全局数据源，而是在当前线程中临时替换 current()，互不影响且与执行顺序无关
+#[cfg(test)]
+pub mod fixture {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    thread_local! {
+        pub(super) static OVERRIDE: Cell<Option<&'static Source>> = const { Cell::new(None) };
+    }
+
+    /// 临时目录中的快照，存在期间当前线程的 current() 指向其中的 proc 与 sys，离开作用域时删除目录
+    pub struct Fixture {
+        root: PathBuf,
+        previous: Option<&'static Source>,
+    }
+
+    impl Fixture {
+        pub fn new() -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let root = std::env::temp_dir().join(format!(
+                "xmonitor-fixture-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed),
+            ));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(root.join("proc")).unwrap();
+            std::fs::create_dir_all(root.join("sys")).unwrap();
+
+            // current() 返回 'static 引用，测试中泄漏这一个小对象即可
+            let source: &'static Source = Box::leak(Box::new(Source::new(Some(root.join("proc")), Some(root.join("sys")))));
+            let previous = OVERRIDE.with(|cell| cell.replace(Some(source)));
+            Fixture { root, previous }
+        }
+
+        pub fn root(&self) -> &Path {
+            &self.root
+        }
+
+        /// 写入快照中的文件，files 为 (相对快照根目录的路径, 内容)
+        pub fn write(&self, files: &[(&str, &str)]) {
+            for (path, content) in files {
+                let path = self.root.join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, content).unwrap();
+            }
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            OVERRIDE.with(|cell| cell.set(self.previous));
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use crate::{source, util};
+
+/// 线程耗尽预警阈值
+#[derive(Debug, Clone, Args)]
+pub struct ThreadLimitConfig {
+    /// 线程数达到限制的这个百分比时发出 warning
+    #[arg(long, value_name = "PERCENT", default_value_t = 80.0)]
+    pub thread_warn_percent: f64,
+    /// 线程数达到限制的这个百分比时发出 critical
+    #[arg(long, value_name = "PERCENT", default_value_t = 95.0)]
+    pub thread_critical_percent: f64,
+}
+
+impl Default for ThreadLimitConfig {
+    fn default() -> Self {
+        ThreadLimitConfig {
+            thread_warn_percent: 80.0,
+            thread_critical_percent: 95.0,
+        }
+    }
+}
+
+/// 单条线程耗尽预警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadWarning {
+    /// warning 或 critical
+    pub level: String,
+    /// system、cgroup 或 process
+    pub scope: String,
+    /// 触发的限制：threads-max、pid_max、pids.max 或 RLIMIT_NPROC
+    pub limit: String,
+    pub current: u64,
+    pub max: u64,
+    /// 相对限制的使用率 (%)
+    pub usage: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "userName", default, skip_serializing_if = "Option::is_none")]
+    pub user_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<String>,
+    pub message: String,
+}
+
+/// 线程数与各级限制的对比结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadLimitReport {
+    pub timestamp: u64,
+    /// 系统中的线程总数
+    #[serde(rename = "totalThreads")]
+    pub total_threads: u64,
+    /// kernel.threads-max
+    #[serde(rename = "threadsMax", default, skip_serializing_if = "Option::is_none")]
+    pub threads_max: Option<u64>,
+    /// kernel.pid_max，每个线程都占用一个 pid
+    #[serde(rename = "pidMax", default, skip_serializing_if = "Option::is_none")]
+    pub pid_max: Option<u64>,
+    pub warnings: Vec<ThreadWarning>,
+}
+
+/// 读取 /proc/sys/kernel 下的限制，例如 threads-max、pid_max
+pub fn read_kernel_limit(name: &str) -> Option<u64> {
+    source::current().read_proc(format!("sys/kernel/{}", name)).ok()?.trim().parse().ok()
+}
+
+impl ThreadLimitConfig {
+    /// 检查阈值设置，warning 阈值不能高于 critical 阈值
+    pub fn validate(&self) -> Result<(), String> {
+        if self.thread_warn_percent > self.thread_critical_percent {
+            return Err(format!(
+                "--thread-warn-percent ({}) must not be greater than --thread-critical-percent ({})",
+                self.thread_warn_percent, self.thread_critical_percent,
+            ));
+        }
+        Ok(())
+    }
+
+    /// 达到阈值时生成预警，未达到时返回 None
+    ///
+    /// 预警的 pid、进程名等由调用方补充
+    pub fn check(&self, scope: &str, limit: &str, current: u64, max: u64) -> Option<ThreadWarning> {
+        if max == 0 {
+            return None;
+        }
+        let usage = current as f64 / max as f64 * 100.0;
+        let level = if usage >= self.thread_critical_percent {
+            "critical"
+        } else if usage >= self.thread_warn_percent {
+            "warning"
+        } else {
+            return None;
+        };
+
+        Some(ThreadWarning {
+            level: level.to_string(),
+            scope: scope.to_string(),
+            limit: limit.to_string(),
+            current,
+            max,
+            usage: util::round1(usage),
+            pid: None,
+            name: None,
+            user_name: None,
+            cgroup: None,
+            message: format!("{} threads reached {:.1}% of {} ({}/{})", scope, usage, limit, current, max),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warn_threshold_above_critical_is_rejected() {
+        let config = ThreadLimitConfig { thread_warn_percent: 99.0, thread_critical_percent: 50.0 };
+        assert!(config.validate().is_err());
+        assert!(ThreadLimitConfig::default().validate().is_ok());
+
+        let config = ThreadLimitConfig { thread_warn_percent: 90.0, thread_critical_percent: 90.0 };
+        assert!(config.validate().is_ok());
+        assert_eq!(config.check("system", "pid_max", 95, 100).unwrap().level, "critical");
+        assert!(config.check("system", "pid_max", 89, 100).is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use clap::Args;
+use std::collections::{HashMap, VecDeque};
+use crate::process::TrendData;
+
+/// 默认为每个进程保留的趋势样本数
+const DEFAULT_TREND_SAMPLES: usize = 60;
+
+/// 进程趋势历史配置
+#[derive(Debug, Clone, Args)]
+pub struct TrendConfig {
+    /// 每个进程最多保留的趋势样本数
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_TREND_SAMPLES)]
+    pub trend_samples: usize,
+    /// 只保留最近这段时间内的趋势样本（秒）
+    #[arg(long, value_name = "SECS")]
+    pub trend_window: Option<u64>,
+}
+
+impl Default for TrendConfig {
+    fn default() -> Self {
+        TrendConfig {
+            trend_samples: DEFAULT_TREND_SAMPLES,
+            trend_window: None,
+        }
+    }
+}
+
+/// 各进程的趋势历史，在多次采集之间保留
+///
+/// 以 (pid, 启动时间) 为键，pid 被复用时不会与旧进程的历史混在一起
+#[derive(Debug, Default)]
+pub struct TrendHistory {
+    config: TrendConfig,
+    samples: HashMap<(u32, u64), VecDeque<TrendData>>,
+}
+
+impl TrendHistory {
+    pub fn new(config: TrendConfig) -> Self {
+        TrendHistory {
+            config,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// 记录一个样本，返回该进程保留的历史（最新的在前，与 data.json 一致）
+    pub fn record(&mut self, pid: u32, start_time: u64, sample: TrendData) -> Vec<TrendData> {
+        let capacity = self.config.trend_samples.max(1);
+        let oldest = self.config.trend_window
+            .map(|secs| sample.timestamp.saturating_sub(secs * 1000));
+
+        let history = self.samples.entry((pid, start_time)).or_default();
+        history.push_back(sample);
+        while history.len() > capacity {
+            history.pop_front();
+        }
+        if let Some(oldest) = oldest {
+            // 最新的样本总是保留
+            while history.len() > 1 && history.front().is_some_and(|s| s.timestamp < oldest) {
+                history.pop_front();
+            }
+        }
+
+        history.iter().rev().cloned().collect()
+    }
+
+    /// 丢弃已退出进程的历史
+    pub fn retain(&mut self, alive: impl Fn(u32, u64) -> bool) {
+        self.samples.retain(|(pid, start_time), _| alive(*pid, *start_time));
+    }
+}
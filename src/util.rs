@@ -1,14 +1,47 @@
 use sysinfo::System;
+use local_ip_address::local_ip;
+use crate::{capture, source};
 
 /// 生成唯一的服务器ID
-/// 格式: hostname-machineId前8位
+/// 格式: hostname-machineId前8位，读取目录快照时取自快照
 pub fn generate_server_id() -> String {
     // 获取主机名
-    let hostname = System::host_name().unwrap_or_else(|| "unknown".to_string());
+    let source = source::current();
+    let hostname = if source.is_live() {
+        System::host_name()
+    } else {
+        source.read_proc("sys/kernel/hostname").ok().map(|name| name.trim().to_string())
+    }
+    .unwrap_or_else(|| "unknown".to_string());
 
     // 获取机器唯一ID
-    let machine_id = machine_uid::get().unwrap_or_else(|_| "unknown".to_string());
+    let machine_id = machine_id();
 
     // 组合生成唯一的服务器ID: hostname-machineId前8位
     format!("{}-{}", hostname, &machine_id[..8.min(machine_id.len())])
 }
+
+/// 机器唯一ID，读取目录快照时取自快照录制的主机标识
+pub fn machine_id() -> String {
+    if source::current().is_live() {
+        machine_uid::get().ok()
+    } else {
+        capture::read_identity().map(|identity| identity.machine_id)
+    }
+    .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 服务器IP，读取目录快照时取自快照录制的主机标识
+pub fn server_ip() -> String {
+    if source::current().is_live() {
+        local_ip().ok().map(|ip| ip.to_string())
+    } else {
+        capture::read_identity().map(|identity| identity.server_ip)
+    }
+    .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 保留一位小数
+pub fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}